@@ -0,0 +1,171 @@
+use regex::Regex;
+
+use crate::{
+    abstraction::{SqlStatement, SupabaseProject},
+    errors::NoWay,
+};
+
+static NAME: &str = r#"(?:"(?:[^"]|"")+"|[\w$]+)(?:\s*\.\s*(?:"(?:[^"]|"")+"|[\w$]+))?"#;
+
+lazy_static::lazy_static! {
+    static ref ROUTINE: Regex = Regex::new(&format!(
+        r"(?is)^\s*create\s+(?:or\s+replace\s+)?(function|procedure)\s+({NAME})\s*\("
+    ))
+    .no_way_because("the pattern is a valid regex");
+
+    static ref VIEW: Regex = Regex::new(&format!(
+        r"(?is)^\s*create\s+(?:or\s+replace\s+)?(?:(?:temp|temporary)\s+)?(?:recursive\s+)?(materialized\s+)?view\s+(?:if\s+not\s+exists\s+)?({NAME})"
+    ))
+    .no_way_because("the pattern is a valid regex");
+
+    static ref TRIGGER: Regex = Regex::new(&format!(
+        r"(?is)^\s*create\s+(?:or\s+replace\s+)?(?:constraint\s+)?trigger\s+({NAME})\s.*?\bon\s+({NAME})"
+    ))
+    .no_way_because("the pattern is a valid regex");
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum RoutineKind {
+    Function,
+    Procedure,
+}
+
+impl RoutineKind {
+    /// Value of `pg_proc.prokind`
+    pub fn prokind(&self) -> &'static str {
+        match self {
+            Self::Function => "f",
+            Self::Procedure => "p",
+        }
+    }
+}
+
+/// Object created by a statement which has to be dropped before the statement can be re-run
+/// after its definition changes in an incompatible way
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CreatedObject {
+    /// Every overload of the routine is dropped, as the new signature can't be told from the
+    /// existing ones without asking Postgres to resolve types
+    Routine {
+        kind: RoutineKind,
+        schema: Option<String>,
+        name: String,
+    },
+
+    View {
+        materialized: bool,
+        name: String,
+    },
+
+    Trigger {
+        name: String,
+        table: String,
+    },
+}
+
+impl CreatedObject {
+    pub fn detect(statement: &SqlStatement) -> Option<Self> {
+        let code = statement.code();
+
+        if let Some(captures) = ROUTINE.captures(&code) {
+            let kind = if captures[1].eq_ignore_ascii_case("function") {
+                RoutineKind::Function
+            } else {
+                RoutineKind::Procedure
+            };
+
+            let (schema, name) = split_name(&captures[2]);
+
+            return Some(Self::Routine { kind, schema, name });
+        }
+
+        if let Some(captures) = VIEW.captures(&code) {
+            return Some(Self::View {
+                materialized: captures.get(1).is_some(),
+                name: captures[2].to_string(),
+            });
+        }
+
+        if let Some(captures) = TRIGGER.captures(&code) {
+            return Some(Self::Trigger {
+                name: captures[1].to_string(),
+                table: captures[2].to_string(),
+            });
+        }
+
+        None
+    }
+
+    /// Drop statements for objects created by the script, triggers go first and routines last
+    /// as the former usually depend on the latter
+    pub async fn drop_statements(
+        project: &SupabaseProject,
+        statements: &[SqlStatement],
+    ) -> anyhow::Result<Vec<String>> {
+        let mut objects = statements
+            .iter()
+            .filter_map(Self::detect)
+            .collect::<Vec<_>>();
+
+        objects.sort_by_key(|object| match object {
+            Self::Trigger { .. } => 0,
+            Self::View { .. } => 1,
+            Self::Routine { .. } => 2,
+        });
+
+        objects.dedup();
+
+        let mut drops = Vec::new();
+
+        for object in objects {
+            match object {
+                Self::Routine { kind, schema, name } => {
+                    for signature in project
+                        .routine_signatures(kind, schema.as_deref(), &name)
+                        .await?
+                    {
+                        drops.push(format!("drop {kind} {signature}"));
+                    }
+                }
+                Self::View { materialized, name } => {
+                    let materialized = if materialized { "materialized " } else { "" };
+                    drops.push(format!("drop {materialized}view if exists {name}"));
+                }
+                Self::Trigger { name, table } => {
+                    drops.push(format!("drop trigger if exists {name} on {table}"));
+                }
+            }
+        }
+
+        Ok(drops)
+    }
+}
+
+/// Splits a possibly schema-qualified name into unquoted parts, folding unquoted ones to
+/// lowercase like Postgres does
+fn split_name(name: &str) -> (Option<String>, String) {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = name.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            '.' if !quoted => parts.push(std::mem::take(&mut current)),
+            char if !quoted && char.is_whitespace() => {}
+            char if !quoted => current.extend(char.to_lowercase()),
+            char => current.push(char),
+        }
+    }
+
+    parts.push(current);
+
+    let name = parts.pop().unwrap_or_default();
+    (parts.pop(), name)
+}
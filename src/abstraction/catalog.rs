@@ -0,0 +1,1299 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::abstraction::{REALTIME_PUBLICATION, SupabaseProject, quote_ident, quote_literal};
+
+/// Condition excluding objects installed by extensions
+fn not_extension_member(oid: &str) -> String {
+    format!(
+        "not exists (select 1 from pg_depend dep where dep.objid = {oid} and dep.deptype = 'e')"
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Column {
+    name: String,
+    data_type: String,
+    not_null: bool,
+
+    /// Default, or the expression of a generated column
+    default: Option<String>,
+
+    /// `a` for `generated always as identity`, `d` for `by default`, empty otherwise
+    identity: String,
+
+    /// `s` for stored generated columns, empty otherwise
+    generated: String,
+}
+
+impl Column {
+    fn sql(&self) -> String {
+        let mut sql = format!("{} {}", quote_ident(&self.name), self.data_type);
+
+        match (self.generated.as_str(), &self.default) {
+            ("s", Some(expression)) => {
+                sql.push_str(&format!(" generated always as ({expression}) stored"))
+            }
+            (_, Some(default)) => sql.push_str(&format!(" default {default}")),
+            _ => {}
+        }
+
+        match self.identity.as_str() {
+            "a" => sql.push_str(" generated always as identity"),
+            "d" => sql.push_str(" generated by default as identity"),
+            _ => {}
+        }
+
+        if self.not_null {
+            sql.push_str(" not null");
+        }
+
+        sql
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Table {
+    columns: Vec<Column>,
+
+    /// Definitions by constraint name
+    constraints: BTreeMap<String, String>,
+    row_level_security: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Routine {
+    /// `function` or `procedure`
+    kind: String,
+    result: Option<String>,
+    definition: String,
+
+    /// Tables whose row types it takes or returns, it's created after them
+    row_types: Vec<String>,
+}
+
+/// Objects of a single schema the native diff compares, read straight from the catalogs
+#[derive(Debug, Default)]
+pub struct Catalog {
+    schema: String,
+    exists: bool,
+    enums: BTreeMap<String, Vec<String>>,
+    tables: BTreeMap<String, Table>,
+    indexes: BTreeMap<String, String>,
+    routines: BTreeMap<String, Routine>,
+    views: BTreeMap<String, String>,
+
+    /// Output columns of each view with their types, `create or replace view` can only append
+    /// to them
+    view_columns: BTreeMap<String, Vec<String>>,
+
+    /// Relations and routines each view depends on, by view name
+    view_dependencies: BTreeMap<String, BTreeSet<String>>,
+
+    /// Other schemas views and foreign keys refer to
+    foreign_schemas: BTreeSet<String>,
+
+    /// Definitions by table and trigger name
+    triggers: BTreeMap<(String, String), String>,
+
+    /// `create policy` statements by table and policy name
+    policies: BTreeMap<(String, String), String>,
+
+    /// Objects the native diff can't generate statements for, by kind and name, the diff gives
+    /// up when any of them differs
+    unsupported: BTreeMap<String, String>,
+}
+
+impl Catalog {
+    pub async fn load(project: &SupabaseProject, schema: &str) -> anyhow::Result<Self> {
+        let mut catalog = Self {
+            schema: schema.to_string(),
+            ..Default::default()
+        };
+
+        let rows = project
+            .runtime()
+            .query(
+                "select 1 from pg_namespace where nspname::text = $1",
+                &[&schema],
+            )
+            .await?;
+
+        catalog.exists = !rows.is_empty();
+
+        for row in project
+            .runtime()
+            .query(
+                &format!(
+                    "select format('%I.%I', n.nspname, t.typname), array_agg(e.enumlabel::text order by e.enumsortorder)
+                    from pg_type t
+                    join pg_namespace n on n.oid = t.typnamespace
+                    join pg_enum e on e.enumtypid = t.oid
+                    where n.nspname::text = $1 and {}
+                    group by 1",
+                    not_extension_member("t.oid"),
+                ),
+                &[&schema],
+            )
+            .await?
+        {
+            catalog.enums.insert(row.get(0), row.get(1));
+        }
+
+        for row in project
+            .runtime()
+            .query(
+                &format!(
+                    "select format('%I.%I', n.nspname, c.relname), c.relrowsecurity
+                    from pg_class c
+                    join pg_namespace n on n.oid = c.relnamespace
+                    where n.nspname::text = $1 and c.relkind = 'r' and not c.relispartition and {}",
+                    not_extension_member("c.oid"),
+                ),
+                &[&schema],
+            )
+            .await?
+        {
+            catalog.tables.insert(
+                row.get(0),
+                Table {
+                    row_level_security: row.get(1),
+                    ..Default::default()
+                },
+            );
+        }
+
+        for row in project
+            .runtime()
+            .query(
+                "select format('%I.%I', n.nspname, c.relname), a.attname::text,
+                    format_type(a.atttypid, a.atttypmod), a.attnotnull,
+                    pg_get_expr(d.adbin, d.adrelid), a.attidentity::text, a.attgenerated::text
+                from pg_attribute a
+                join pg_class c on c.oid = a.attrelid
+                join pg_namespace n on n.oid = c.relnamespace
+                left join pg_attrdef d on d.adrelid = a.attrelid and d.adnum = a.attnum
+                where n.nspname::text = $1 and c.relkind = 'r' and a.attnum > 0 and not a.attisdropped
+                order by 1, a.attnum",
+                &[&schema],
+            )
+            .await?
+        {
+            let Some(table) = catalog.tables.get_mut(&row.get::<_, String>(0)) else {
+                continue;
+            };
+
+            table.columns.push(Column {
+                name: row.get(1),
+                data_type: row.get(2),
+                not_null: row.get(3),
+                default: row.get(4),
+                identity: row.get(5),
+                generated: row.get(6),
+            });
+        }
+
+        for row in project
+            .runtime()
+            .query(
+                "select format('%I.%I', n.nspname, c.relname), con.conname::text, pg_get_constraintdef(con.oid)
+                from pg_constraint con
+                join pg_class c on c.oid = con.conrelid
+                join pg_namespace n on n.oid = c.relnamespace
+                where n.nspname::text = $1 and c.relkind = 'r' and con.contype <> 'n'",
+                &[&schema],
+            )
+            .await?
+        {
+            if let Some(table) = catalog.tables.get_mut(&row.get::<_, String>(0)) {
+                table.constraints.insert(row.get(1), row.get(2));
+            }
+        }
+
+        for row in project
+            .runtime()
+            .query(
+                &format!(
+                    "select format('%I.%I', n.nspname, i.relname), pg_get_indexdef(i.oid)
+                    from pg_index x
+                    join pg_class i on i.oid = x.indexrelid
+                    join pg_class c on c.oid = x.indrelid
+                    join pg_namespace n on n.oid = i.relnamespace
+                    where n.nspname::text = $1 and c.relkind = 'r' and {}
+                        and not exists (
+                            select 1 from pg_constraint con
+                            where con.conindid = i.oid and con.contype in ('p', 'u', 'x')
+                        )",
+                    not_extension_member("i.oid"),
+                ),
+                &[&schema],
+            )
+            .await?
+        {
+            catalog.indexes.insert(row.get(0), row.get(1));
+        }
+
+        for row in project
+            .runtime()
+            .query(
+                &format!(
+                    "select format('%I.%I(%s)', n.nspname, p.proname, pg_get_function_identity_arguments(p.oid)),
+                        case p.prokind when 'p' then 'procedure' else 'function' end,
+                        pg_get_function_result(p.oid), pg_get_functiondef(p.oid),
+                        array(
+                            select format('%I.%I', tn.nspname, tc.relname)
+                            from pg_type ty
+                            join pg_class tc on tc.oid = ty.typrelid
+                            join pg_namespace tn on tn.oid = tc.relnamespace
+                            where tc.relkind = 'r' and (
+                                ty.oid = any(coalesce(p.proallargtypes, p.proargtypes::oid[]) || p.prorettype)
+                                or ty.typarray = any(coalesce(p.proallargtypes, p.proargtypes::oid[]) || p.prorettype)
+                            )
+                        )
+                    from pg_proc p
+                    join pg_namespace n on n.oid = p.pronamespace
+                    where n.nspname::text = $1 and p.prokind in ('f', 'p') and {}",
+                    not_extension_member("p.oid"),
+                ),
+                &[&schema],
+            )
+            .await?
+        {
+            catalog.routines.insert(
+                row.get(0),
+                Routine {
+                    kind: row.get(1),
+                    result: row.get(2),
+                    definition: row.get(3),
+                    row_types: row.get(4),
+                },
+            );
+        }
+
+        for row in project
+            .runtime()
+            .query(
+                &format!(
+                    "select format('%I.%I', n.nspname, c.relname), pg_get_viewdef(c.oid),
+                        array_to_string(c.reloptions, ', '),
+                        array(
+                            select format('%I %s', a.attname, format_type(a.atttypid, a.atttypmod))
+                            from pg_attribute a
+                            where a.attrelid = c.oid and a.attnum > 0 and not a.attisdropped
+                            order by a.attnum
+                        )
+                    from pg_class c
+                    join pg_namespace n on n.oid = c.relnamespace
+                    where n.nspname::text = $1 and c.relkind = 'v' and {}",
+                    not_extension_member("c.oid"),
+                ),
+                &[&schema],
+            )
+            .await?
+        {
+            let name: String = row.get(0);
+            let definition: String = row.get(1);
+            let options = row
+                .get::<_, Option<String>>(2)
+                .filter(|options| !options.is_empty())
+                .map(|options| format!(" with ({options})"))
+                .unwrap_or_default();
+
+            catalog.view_columns.insert(name.clone(), row.get(3));
+            catalog.views.insert(
+                name.clone(),
+                format!(
+                    "create or replace view {name}{options} as\n{};",
+                    definition.trim_end().trim_end_matches(';')
+                ),
+            );
+        }
+
+        for row in project
+            .runtime()
+            .query(
+                "select format('%I.%I', n.nspname, v.relname), rn.nspname::text,
+                    case when r.oid is null
+                        then format('%I.%I(%s)', rn.nspname, p.proname, pg_get_function_identity_arguments(p.oid))
+                        else format('%I.%I', rn.nspname, r.relname)
+                    end
+                from pg_rewrite w
+                join pg_class v on v.oid = w.ev_class
+                join pg_namespace n on n.oid = v.relnamespace
+                join pg_depend d on d.classid = 'pg_rewrite'::regclass and d.objid = w.oid
+                left join pg_class r on d.refclassid = 'pg_class'::regclass and r.oid = d.refobjid
+                left join pg_proc p on d.refclassid = 'pg_proc'::regclass and p.oid = d.refobjid
+                join pg_namespace rn on rn.oid = coalesce(r.relnamespace, p.pronamespace)
+                where n.nspname::text = $1 and v.relkind = 'v' and d.refobjid <> v.oid
+                union
+                select format('%I.%I', n.nspname, c.relname), rn.nspname::text, format('%I.%I', rn.nspname, r.relname)
+                from pg_constraint con
+                join pg_class c on c.oid = con.conrelid
+                join pg_namespace n on n.oid = c.relnamespace
+                join pg_class r on r.oid = con.confrelid
+                join pg_namespace rn on rn.oid = r.relnamespace
+                where n.nspname::text = $1 and con.contype = 'f'",
+                &[&schema],
+            )
+            .await?
+        {
+            let name: String = row.get(0);
+            let referenced_schema: String = row.get(1);
+
+            if referenced_schema != schema {
+                catalog.foreign_schemas.insert(referenced_schema);
+            }
+
+            if catalog.views.contains_key(&name) {
+                catalog
+                    .view_dependencies
+                    .entry(name)
+                    .or_default()
+                    .insert(row.get(2));
+            }
+        }
+
+        for row in project
+            .runtime()
+            .query(
+                "select format('%I.%I', n.nspname, c.relname), t.tgname::text, pg_get_triggerdef(t.oid)
+                from pg_trigger t
+                join pg_class c on c.oid = t.tgrelid
+                join pg_namespace n on n.oid = c.relnamespace
+                where n.nspname::text = $1 and not t.tgisinternal",
+                &[&schema],
+            )
+            .await?
+        {
+            catalog
+                .triggers
+                .insert((row.get(0), row.get(1)), format!("{};", row.get::<_, String>(2)));
+        }
+
+        for row in project
+            .runtime()
+            .query(
+                "select format('%I.%I', schemaname, tablename), policyname::text, permissive,
+                    roles::text[], cmd, qual, with_check
+                from pg_policies
+                where schemaname::text = $1",
+                &[&schema],
+            )
+            .await?
+        {
+            let table: String = row.get(0);
+            let name: String = row.get(1);
+            let permissive: String = row.get(2);
+            let roles: Vec<String> = row.get(3);
+            let command: String = row.get(4);
+
+            let roles = roles
+                .iter()
+                .map(|role| match role.as_str() {
+                    "public" => role.clone(),
+                    _ => quote_ident(role),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let mut sql = format!(
+                "create policy {} on {table}\n  as {} for {} to {}",
+                quote_ident(&name),
+                permissive.to_lowercase(),
+                command.to_lowercase(),
+                roles
+            );
+
+            if let Some(qual) = row.get::<_, Option<String>>(5) {
+                sql.push_str(&format!("\n  using ({qual})"));
+            }
+
+            if let Some(check) = row.get::<_, Option<String>>(6) {
+                sql.push_str(&format!("\n  with check ({check})"));
+            }
+
+            sql.push(';');
+            catalog.policies.insert((table, name), sql);
+        }
+
+        for row in project
+            .runtime()
+            .query(
+                &format!(
+                    "select 'type ' || format('%I.%I', n.nspname, t.typname),
+                        t.typtype::text || ' ' || coalesce(format_type(t.typbasetype, t.typtypmod), '')
+                    from pg_type t
+                    join pg_namespace n on n.oid = t.typnamespace
+                    left join pg_class c on c.oid = t.typrelid
+                    where n.nspname::text = $1 and {}
+                        and (t.typtype in ('d', 'r', 'm') or (t.typtype = 'c' and c.relkind = 'c'))
+                    union all
+                    select 'sequence ' || format('%I.%I', n.nspname, c.relname), ''
+                    from pg_class c
+                    join pg_namespace n on n.oid = c.relnamespace
+                    where n.nspname::text = $1 and c.relkind = 'S' and {}
+                        and not exists (
+                            select 1 from pg_depend d
+                            where d.classid = 'pg_class'::regclass and d.objid = c.oid and d.deptype = 'i'
+                        )
+                    union all
+                    select case c.relkind
+                            when 'm' then 'materialized view '
+                            when 'p' then 'partitioned table '
+                            when 'f' then 'foreign table '
+                            else 'partition '
+                        end || format('%I.%I', n.nspname, c.relname),
+                        case when c.relkind = 'm' then pg_get_viewdef(c.oid) else '' end
+                    from pg_class c
+                    join pg_namespace n on n.oid = c.relnamespace
+                    where n.nspname::text = $1 and {}
+                        and (c.relkind in ('m', 'p', 'f') or c.relispartition)
+                    union all
+                    select 'aggregate ' || format('%I.%I(%s)', n.nspname, p.proname, pg_get_function_identity_arguments(p.oid)), ''
+                    from pg_proc p
+                    join pg_namespace n on n.oid = p.pronamespace
+                    where n.nspname::text = $1 and p.prokind in ('a', 'w') and {}
+                    union all
+                    select 'extension ' || quote_ident(extname), extversion
+                    from pg_extension",
+                    not_extension_member("t.oid"),
+                    not_extension_member("c.oid"),
+                    not_extension_member("c.oid"),
+                    not_extension_member("p.oid"),
+                ),
+                &[&schema],
+            )
+            .await?
+        {
+            catalog.unsupported.insert(row.get(0), row.get(1));
+        }
+
+        // Owners, privileges, comments, collations and publication membership aren't diffed
+        // either, they're listed only when they aren't what a plain `create` gives, so new
+        // objects don't count as changes unless they've been altered
+        for row in project
+            .runtime()
+            .query(
+                &format!(
+                    "with objects (name, acl_kind, default_kind, namespace, owner, acl, description) as (
+                        select format('%I.%I', n.nspname, c.relname),
+                            case c.relkind when 'S' then 's' else 'r' end,
+                            case c.relkind when 'S' then 'S' else 'r' end,
+                            c.relnamespace, c.relowner, c.relacl, obj_description(c.oid, 'pg_class')
+                        from pg_class c
+                        join pg_namespace n on n.oid = c.relnamespace
+                        where n.nspname::text = $1 and c.relkind in ('r', 'v', 'm', 'S', 'p', 'f') and {}
+                        union all
+                        select format('%I.%I(%s)', n.nspname, p.proname, pg_get_function_identity_arguments(p.oid)),
+                            'f', 'f', p.pronamespace, p.proowner, p.proacl, obj_description(p.oid, 'pg_proc')
+                        from pg_proc p
+                        join pg_namespace n on n.oid = p.pronamespace
+                        where n.nspname::text = $1 and {}
+                        union all
+                        select format('%I.%I', n.nspname, t.typname),
+                            'T', 'T', t.typnamespace, t.typowner, t.typacl, obj_description(t.oid, 'pg_type')
+                        from pg_type t
+                        join pg_namespace n on n.oid = t.typnamespace
+                        left join pg_class c on c.oid = t.typrelid
+                        where n.nspname::text = $1 and {}
+                            and (t.typtype in ('e', 'd', 'r', 'm') or (t.typtype = 'c' and c.relkind = 'c'))
+                        union all
+                        select 'schema ' || quote_ident(n.nspname),
+                            'n', 'n', 0::oid, n.nspowner, n.nspacl, obj_description(n.oid, 'pg_namespace')
+                        from pg_namespace n
+                        where n.nspname::text = $1
+                    ),
+                    privileges (name, actual, expected) as (
+                        select name,
+                            array(
+                                select distinct format('%s %s %s %s', e.grantor::regrole, e.grantee::regrole, e.privilege_type, e.is_grantable)
+                                from aclexplode(coalesce(acl, acldefault(acl_kind::\"char\", owner))) e
+                                order by 1
+                            ),
+                            array(
+                                select format('%s %s %s %s', e.grantor::regrole, e.grantee::regrole, e.privilege_type, e.is_grantable)
+                                from aclexplode(coalesce(
+                                    (
+                                        select defaclacl from pg_default_acl
+                                        where defaclrole = owner and defaclnamespace = 0 and defaclobjtype::text = default_kind
+                                    ),
+                                    acldefault(acl_kind::\"char\", owner)
+                                )) e
+                                union
+                                select format('%s %s %s %s', e.grantor::regrole, e.grantee::regrole, e.privilege_type, e.is_grantable)
+                                from aclexplode((
+                                    select defaclacl from pg_default_acl
+                                    where defaclrole = owner and defaclnamespace = namespace and defaclobjtype::text = default_kind
+                                )) e
+                                order by 1
+                            )
+                        from objects
+                    )
+                    select 'privileges of ' || name, array_to_string(actual, ', ')
+                    from privileges
+                    where actual <> expected
+                    union all
+                    select 'owner of ' || name, pg_get_userbyid(owner)::text
+                    from objects
+                    where pg_get_userbyid(owner) <> current_user
+                    union all
+                    select 'comment on ' || name, description
+                    from objects
+                    where description is not null
+                    union all
+                    select 'privileges of ' || format('%I.%I.%I', n.nspname, c.relname, a.attname), a.attacl::text
+                    from pg_attribute a
+                    join pg_class c on c.oid = a.attrelid
+                    join pg_namespace n on n.oid = c.relnamespace
+                    where n.nspname::text = $1 and a.attnum > 0 and not a.attisdropped and a.attacl is not null
+                    union all
+                    select 'comment on ' || format('%I.%I.%I', n.nspname, c.relname, a.attname), col_description(c.oid, a.attnum)
+                    from pg_attribute a
+                    join pg_class c on c.oid = a.attrelid
+                    join pg_namespace n on n.oid = c.relnamespace
+                    where n.nspname::text = $1 and a.attnum > 0 and not a.attisdropped
+                        and col_description(c.oid, a.attnum) is not null
+                    union all
+                    select 'collation of ' || format('%I.%I.%I', n.nspname, c.relname, a.attname), a.attcollation::regcollation::text
+                    from pg_attribute a
+                    join pg_type t on t.oid = a.atttypid
+                    join pg_class c on c.oid = a.attrelid
+                    join pg_namespace n on n.oid = c.relnamespace
+                    where n.nspname::text = $1 and a.attnum > 0 and not a.attisdropped
+                        and a.attcollation <> t.typcollation
+                    union all
+                    select 'publication ' || quote_ident(p.pubname) || ' membership of ' || format('%I.%I', n.nspname, c.relname),
+                        concat_ws(' ', pg_get_expr(pr.prqual, pr.prrelid), pr.prattrs::text)
+                    from pg_publication_rel pr
+                    join pg_publication p on p.oid = pr.prpubid
+                    join pg_class c on c.oid = pr.prrelid
+                    join pg_namespace n on n.oid = c.relnamespace
+                    where n.nspname::text = $1 and p.pubname <> {}
+                    union all
+                    select 'publication ' || quote_ident(p.pubname) || ' membership of schema ' || quote_ident(n.nspname), ''
+                    from pg_publication_namespace pn
+                    join pg_publication p on p.oid = pn.pnpubid
+                    join pg_namespace n on n.oid = pn.pnnspid
+                    where n.nspname::text = $1",
+                    not_extension_member("c.oid"),
+                    not_extension_member("p.oid"),
+                    not_extension_member("t.oid"),
+                    // Membership in it is brought over along with buckets
+                    quote_literal(REALTIME_PUBLICATION),
+                ),
+                &[&schema],
+            )
+            .await?
+        {
+            catalog.unsupported.insert(row.get(0), row.get(1));
+        }
+
+        Ok(catalog)
+    }
+
+    /// Statements turning `self` (e.g. the schema produced by migrations) into `after` (e.g. the
+    /// local one), fails when objects the native diff doesn't support differ
+    pub fn diff(&self, after: &Catalog) -> anyhow::Result<Vec<String>> {
+        let unsupported = self
+            .unsupported
+            .iter()
+            .filter(|(key, value)| after.unsupported.get(*key) != Some(value))
+            .chain(
+                after
+                    .unsupported
+                    .iter()
+                    .filter(|(key, _)| !self.unsupported.contains_key(*key)),
+            )
+            .map(|(key, _)| key)
+            .next();
+
+        if let Some(key) = unsupported {
+            crate::styled_bail!(
+                "Changes of `{}` aren't supported by the native diff",
+                (key, "id")
+            );
+        }
+
+        let mut statements = Vec::new();
+
+        if after.exists && !self.exists {
+            statements.push(format!(
+                "create schema if not exists {};",
+                quote_ident(&after.schema)
+            ));
+        }
+
+        // Objects depending on others are dropped first
+        for (key, policy) in &self.policies {
+            if after.policies.get(key) != Some(policy) {
+                statements.push(format!("drop policy {} on {};", quote_ident(&key.1), key.0));
+            }
+        }
+
+        for (key, trigger) in &self.triggers {
+            if after.triggers.get(key) != Some(trigger) {
+                statements.push(format!(
+                    "drop trigger {} on {};",
+                    quote_ident(&key.1),
+                    key.0
+                ));
+            }
+        }
+
+        // Views depending on what's dropped or retyped can't stay in place, they're dropped
+        // along with dependent views and re-created once it's done
+        let replaced = self.replaced_views(after);
+
+        for name in self.in_dependency_order(&replaced).iter().rev() {
+            statements.push(format!("drop view {name};"));
+        }
+
+        // Foreign keys go first as they may depend on other constraints' indexes
+        let mut dropped_constraints = Vec::new();
+
+        for (name, table) in &self.tables {
+            let other = after.tables.get(name);
+
+            for (constraint, definition) in &table.constraints {
+                // Other constraints of dropped tables go along with them, while their foreign
+                // keys could keep tables dropped before them in place
+                let dropped = match other {
+                    Some(other) => other.constraints.get(constraint) != Some(definition),
+                    None => definition.starts_with("FOREIGN KEY"),
+                };
+
+                if dropped {
+                    dropped_constraints.push((
+                        !definition.starts_with("FOREIGN KEY"),
+                        format!(
+                            "alter table {name} drop constraint {};",
+                            quote_ident(constraint)
+                        ),
+                    ));
+                }
+            }
+        }
+
+        dropped_constraints.sort_by_key(|(is_foreign_key, _)| *is_foreign_key);
+        statements.extend(
+            dropped_constraints
+                .into_iter()
+                .map(|(_, statement)| statement),
+        );
+
+        for (name, index) in &self.indexes {
+            if after.indexes.get(name) != Some(index) {
+                statements.push(format!("drop index {name};"));
+            }
+        }
+
+        for name in self.tables.keys() {
+            if !after.tables.contains_key(name) {
+                statements.push(format!("drop table {name};"));
+            }
+        }
+
+        for (name, labels) in &after.enums {
+            match self.enums.get(name) {
+                None => statements.push(format!(
+                    "create type {name} as enum ({});",
+                    labels
+                        .iter()
+                        .map(|label| quote_literal(label))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+                Some(before) if before == labels => {}
+                Some(before) if labels.starts_with(before) => {
+                    for label in &labels[before.len()..] {
+                        statements.push(format!(
+                            "alter type {name} add value {};",
+                            quote_literal(label)
+                        ));
+                    }
+                }
+                Some(_) => {
+                    crate::styled_bail!(
+                        "Changes of `{}` other than added values aren't supported by the native diff",
+                        (name, "id")
+                    );
+                }
+            }
+        }
+
+        for (signature, routine) in self.dropped_routines(after) {
+            statements.push(format!("drop {} {signature};", routine.kind));
+        }
+
+        // Routines go before tables, whose defaults and checks may call them, bodies aren't
+        // validated so they can refer to tables created later, only ones taking or returning
+        // rows of new tables wait for them
+        let mut late_routines = Vec::new();
+        let mut early_routines = Vec::new();
+
+        for (signature, routine) in &after.routines {
+            if self.routines.get(signature) == Some(routine) {
+                continue;
+            }
+
+            let statement = format!("{};", routine.definition.trim_end());
+
+            if routine
+                .row_types
+                .iter()
+                .any(|table| after.tables.contains_key(table) && !self.tables.contains_key(table))
+            {
+                late_routines.push(statement);
+            } else {
+                early_routines.push(statement);
+            }
+        }
+
+        if !early_routines.is_empty() {
+            statements.push("set check_function_bodies = off;".to_string());
+            statements.extend(early_routines);
+            statements.push("reset check_function_bodies;".to_string());
+        }
+
+        for (name, table) in &after.tables {
+            let Some(before) = self.tables.get(name) else {
+                statements.push(format!(
+                    "create table {name} (\n{}\n);",
+                    table
+                        .columns
+                        .iter()
+                        .map(|column| format!("  {}", column.sql()))
+                        .collect::<Vec<_>>()
+                        .join(",\n")
+                ));
+
+                continue;
+            };
+
+            statements.extend(Self::column_changes(name, before, table)?);
+        }
+
+        statements.extend(late_routines);
+
+        let views = after
+            .views
+            .iter()
+            .filter(|(name, view)| replaced.contains(*name) || self.views.get(*name) != Some(view))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in after.in_dependency_order(&views) {
+            statements.extend(after.views.get(&name).cloned());
+        }
+
+        let mut added_constraints = Vec::new();
+
+        for (name, table) in &after.tables {
+            let before = self.tables.get(name);
+
+            for (constraint, definition) in &table.constraints {
+                if before.and_then(|before| before.constraints.get(constraint)) != Some(definition)
+                {
+                    added_constraints.push((
+                        definition.starts_with("FOREIGN KEY"),
+                        format!(
+                            "alter table {name} add constraint {} {definition};",
+                            quote_ident(constraint)
+                        ),
+                    ));
+                }
+            }
+        }
+
+        added_constraints.sort_by_key(|(is_foreign_key, _)| *is_foreign_key);
+        statements.extend(
+            added_constraints
+                .into_iter()
+                .map(|(_, statement)| statement),
+        );
+
+        for (name, index) in &after.indexes {
+            if self.indexes.get(name) != Some(index) {
+                statements.push(format!("{index};"));
+            }
+        }
+
+        for (name, table) in &after.tables {
+            let before = self
+                .tables
+                .get(name)
+                .map(|before| before.row_level_security)
+                .unwrap_or_default();
+
+            if table.row_level_security != before {
+                statements.push(format!(
+                    "alter table {name} {} row level security;",
+                    if table.row_level_security {
+                        "enable"
+                    } else {
+                        "disable"
+                    }
+                ));
+            }
+        }
+
+        for (key, trigger) in &after.triggers {
+            if replaced.contains(&key.0) || self.triggers.get(key) != Some(trigger) {
+                statements.push(trigger.clone());
+            }
+        }
+
+        for (key, policy) in &after.policies {
+            if self.policies.get(key) != Some(policy) {
+                statements.push(policy.clone());
+            }
+        }
+
+        for name in self.enums.keys() {
+            if !after.enums.contains_key(name) {
+                statements.push(format!("drop type {name};"));
+            }
+        }
+
+        if self.exists && !after.exists {
+            statements.push(format!("drop schema {};", quote_ident(&self.schema)));
+        }
+
+        Ok(statements)
+    }
+
+    /// Signatures of routines that are gone or whose results have changed, which can't be
+    /// replaced in place
+    fn dropped_routines(&self, after: &Catalog) -> Vec<(&String, &Routine)> {
+        self.routines
+            .iter()
+            .filter(
+                |(signature, routine)| match after.routines.get(*signature) {
+                    Some(other) => other.result != routine.result,
+                    None => true,
+                },
+            )
+            .collect()
+    }
+
+    /// Views to be dropped: gone ones, ones whose existing columns change and ones depending,
+    /// even indirectly, on tables that are dropped or have columns dropped or retyped, or on
+    /// dropped routines
+    fn replaced_views(&self, after: &Catalog) -> BTreeSet<String> {
+        let mut changed = self
+            .tables
+            .iter()
+            .filter(|(name, table)| match after.tables.get(*name) {
+                Some(other) => table.columns.iter().any(|column| {
+                    other
+                        .columns
+                        .iter()
+                        .find(|other| other.name == column.name)
+                        .is_none_or(|other| other.data_type != column.data_type)
+                }),
+                None => true,
+            })
+            .map(|(name, _)| name.clone())
+            .chain(
+                self.dropped_routines(after)
+                    .into_iter()
+                    .map(|(signature, _)| signature.clone()),
+            )
+            .collect::<BTreeSet<_>>();
+
+        let mut replaced = self
+            .views
+            .keys()
+            .filter(|name| {
+                let columns = self.view_columns.get(*name).map_or(&[][..], Vec::as_slice);
+
+                after
+                    .view_columns
+                    .get(*name)
+                    .is_none_or(|other| !other.starts_with(columns))
+            })
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        changed.extend(replaced.iter().cloned());
+
+        loop {
+            let dependent = self
+                .view_dependencies
+                .iter()
+                .filter(|(name, dependencies)| {
+                    !replaced.contains(*name) && !dependencies.is_disjoint(&changed)
+                })
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+
+            if dependent.is_empty() {
+                return replaced;
+            }
+
+            changed.extend(dependent.iter().cloned());
+            replaced.extend(dependent);
+        }
+    }
+
+    /// Views of `names` ordered so each one comes after the views it depends on
+    fn in_dependency_order(&self, names: &BTreeSet<String>) -> Vec<String> {
+        let mut ordered = Vec::new();
+
+        for name in names {
+            self.visit_view(name, names, &mut ordered);
+        }
+
+        ordered
+    }
+
+    fn visit_view(&self, name: &str, names: &BTreeSet<String>, ordered: &mut Vec<String>) {
+        if !names.contains(name) || ordered.iter().any(|other| other == name) {
+            return;
+        }
+
+        for dependency in self.view_dependencies.get(name).into_iter().flatten() {
+            self.visit_view(dependency, names, ordered);
+        }
+
+        ordered.push(name.to_string());
+    }
+
+    /// Other schemas the objects refer to, their statements have to come first
+    pub fn foreign_schemas(&self) -> &BTreeSet<String> {
+        &self.foreign_schemas
+    }
+
+    fn column_changes(name: &str, before: &Table, after: &Table) -> anyhow::Result<Vec<String>> {
+        let mut statements = Vec::new();
+
+        for column in &before.columns {
+            if !after.columns.iter().any(|other| other.name == column.name) {
+                statements.push(format!(
+                    "alter table {name} drop column {};",
+                    quote_ident(&column.name)
+                ));
+            }
+        }
+
+        for column in &after.columns {
+            let Some(previous) = before
+                .columns
+                .iter()
+                .find(|other| other.name == column.name)
+            else {
+                statements.push(format!("alter table {name} add column {};", column.sql()));
+                continue;
+            };
+
+            if previous == column {
+                continue;
+            }
+
+            let generated_changed = previous.generated != column.generated
+                || (column.generated == "s" && previous.default != column.default);
+
+            if previous.identity != column.identity || generated_changed {
+                crate::styled_bail!(
+                    "Changes of identity or generated column `{}` aren't supported by the native diff",
+                    (&format!("{name}.{}", column.name), "id")
+                );
+            }
+
+            let alter = format!(
+                "alter table {name} alter column {}",
+                quote_ident(&column.name)
+            );
+
+            if previous.data_type != column.data_type {
+                statements.push(format!(
+                    "{alter} set data type {} using {}::{};",
+                    column.data_type,
+                    quote_ident(&column.name),
+                    column.data_type
+                ));
+            }
+
+            if previous.default != column.default {
+                statements.push(match &column.default {
+                    Some(default) => format!("{alter} set default {default};"),
+                    None => format!("{alter} drop default;"),
+                });
+            }
+
+            if previous.not_null != column.not_null {
+                statements.push(format!(
+                    "{alter} {} not null;",
+                    if column.not_null { "set" } else { "drop" }
+                ));
+            }
+        }
+
+        Ok(statements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::NoWay;
+
+    fn catalog() -> Catalog {
+        Catalog {
+            schema: "public".into(),
+            exists: true,
+            ..Default::default()
+        }
+    }
+
+    fn column(name: &str, data_type: &str) -> Column {
+        Column {
+            name: name.into(),
+            data_type: data_type.into(),
+            not_null: false,
+            default: None,
+            identity: String::new(),
+            generated: String::new(),
+        }
+    }
+
+    fn table(columns: Vec<Column>, constraints: &[(&str, &str)]) -> Table {
+        Table {
+            columns,
+            constraints: constraints
+                .iter()
+                .map(|(name, definition)| (name.to_string(), definition.to_string()))
+                .collect(),
+            row_level_security: false,
+        }
+    }
+
+    fn with_view(catalog: &mut Catalog, name: &str, definition: &str, columns: &[&str]) {
+        catalog.views.insert(
+            name.into(),
+            format!("create or replace view {name} as\n{definition};"),
+        );
+        catalog.view_columns.insert(
+            name.into(),
+            columns.iter().map(|column| column.to_string()).collect(),
+        );
+    }
+
+    fn position(statements: &[String], prefix: &str) -> usize {
+        statements
+            .iter()
+            .position(|statement| statement.starts_with(prefix))
+            .no_way_because("the statement is expected in the diff")
+    }
+
+    #[test]
+    fn new_tables_are_created_with_their_constraints() {
+        let before = catalog();
+        let mut after = catalog();
+
+        after.tables.insert(
+            "public.items".into(),
+            table(
+                vec![column("id", "bigint"), column("name", "text")],
+                &[("items_pkey", "PRIMARY KEY (id)")],
+            ),
+        );
+
+        assert_eq!(
+            before
+                .diff(&after)
+                .no_way_because("nothing unsupported changed"),
+            vec![
+                "create table public.items (\n  \"id\" bigint,\n  \"name\" text\n);".to_string(),
+                "alter table public.items add constraint \"items_pkey\" PRIMARY KEY (id);"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn foreign_keys_of_dropped_tables_are_dropped_before_tables() {
+        let mut before = catalog();
+        let after = catalog();
+
+        before.tables.insert(
+            "public.a".into(),
+            table(
+                vec![column("id", "bigint")],
+                &[("a_pkey", "PRIMARY KEY (id)")],
+            ),
+        );
+        before.tables.insert(
+            "public.b".into(),
+            table(
+                vec![column("a_id", "bigint")],
+                &[("b_a_id_fkey", "FOREIGN KEY (a_id) REFERENCES a(id)")],
+            ),
+        );
+
+        assert_eq!(
+            before
+                .diff(&after)
+                .no_way_because("nothing unsupported changed"),
+            vec![
+                "alter table public.b drop constraint \"b_a_id_fkey\";".to_string(),
+                "drop table public.a;".to_string(),
+                "drop table public.b;".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn retyped_columns_replace_dependent_views() {
+        let mut before = catalog();
+        let mut after = catalog();
+
+        before
+            .tables
+            .insert("public.a".into(), table(vec![column("id", "integer")], &[]));
+        after
+            .tables
+            .insert("public.a".into(), table(vec![column("id", "bigint")], &[]));
+
+        for catalog in [&mut before, &mut after] {
+            with_view(catalog, "public.v1", "select id from a", &["id integer"]);
+            with_view(catalog, "public.v2", "select id from v1", &["id integer"]);
+            catalog
+                .view_dependencies
+                .insert("public.v1".into(), BTreeSet::from(["public.a".to_string()]));
+            catalog.view_dependencies.insert(
+                "public.v2".into(),
+                BTreeSet::from(["public.v1".to_string()]),
+            );
+        }
+
+        let statements = before
+            .diff(&after)
+            .no_way_because("nothing unsupported changed");
+
+        assert!(
+            position(&statements, "drop view public.v2")
+                < position(&statements, "drop view public.v1")
+        );
+        assert!(
+            position(&statements, "drop view public.v1")
+                < position(&statements, "alter table public.a")
+        );
+        assert!(
+            position(
+                &statements,
+                "alter table public.a alter column \"id\" set data type bigint"
+            ) < position(&statements, "create or replace view public.v1")
+        );
+        assert!(
+            position(&statements, "create or replace view public.v1")
+                < position(&statements, "create or replace view public.v2")
+        );
+    }
+
+    #[test]
+    fn added_enum_values_are_appended() {
+        let mut before = catalog();
+        let mut after = catalog();
+
+        before
+            .enums
+            .insert("public.status".into(), vec!["draft".into()]);
+        after.enums.insert(
+            "public.status".into(),
+            vec!["draft".into(), "published".into()],
+        );
+
+        assert_eq!(
+            before
+                .diff(&after)
+                .no_way_because("nothing unsupported changed"),
+            vec!["alter type public.status add value 'published';".to_string()]
+        );
+
+        after
+            .enums
+            .insert("public.status".into(), vec!["published".into()]);
+
+        assert!(before.diff(&after).is_err());
+    }
+
+    #[test]
+    fn views_changing_existing_columns_are_replaced() {
+        let mut before = catalog();
+        let mut after = catalog();
+
+        with_view(
+            &mut before,
+            "public.v",
+            "select 1 as a, 2 as b",
+            &["a integer", "b integer"],
+        );
+        with_view(
+            &mut after,
+            "public.v",
+            "select 1 as a, 2 as b, 3 as c",
+            &["a integer", "b integer", "c integer"],
+        );
+
+        assert_eq!(
+            before
+                .diff(&after)
+                .no_way_because("nothing unsupported changed"),
+            vec!["create or replace view public.v as\nselect 1 as a, 2 as b, 3 as c;".to_string()]
+        );
+
+        with_view(&mut after, "public.v", "select 2 as b", &["b integer"]);
+
+        assert_eq!(
+            before
+                .diff(&after)
+                .no_way_because("nothing unsupported changed"),
+            vec![
+                "drop view public.v;".to_string(),
+                "create or replace view public.v as\nselect 2 as b;".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn routines_taking_rows_of_new_tables_follow_them() {
+        let before = catalog();
+        let mut after = catalog();
+
+        after.tables.insert(
+            "public.items".into(),
+            table(vec![column("id", "bigint")], &[]),
+        );
+        after.routines.insert(
+            "public.next_id()".into(),
+            Routine {
+                kind: "function".into(),
+                result: Some("bigint".into()),
+                definition: "CREATE OR REPLACE FUNCTION public.next_id() ...".into(),
+                row_types: Vec::new(),
+            },
+        );
+        after.routines.insert(
+            "public.label(item items)".into(),
+            Routine {
+                kind: "function".into(),
+                result: Some("text".into()),
+                definition: "CREATE OR REPLACE FUNCTION public.label(item items) ...".into(),
+                row_types: vec!["public.items".into()],
+            },
+        );
+
+        assert_eq!(
+            before
+                .diff(&after)
+                .no_way_because("nothing unsupported changed"),
+            vec![
+                "set check_function_bodies = off;".to_string(),
+                "CREATE OR REPLACE FUNCTION public.next_id() ...;".to_string(),
+                "reset check_function_bodies;".to_string(),
+                "create table public.items (\n  \"id\" bigint\n);".to_string(),
+                "CREATE OR REPLACE FUNCTION public.label(item items) ...;".to_string(),
+            ]
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use anyhow::Context;
 use derive_setters::Setters;
 use futures_channel::mpsc::{Receiver, Sender, channel};
-use futures_util::{SinkExt, StreamExt, pin_mut};
+use futures_util::{SinkExt, StreamExt};
 use notify_types::event::{CreateKind, ModifyKind};
 use std::{collections::HashSet, path::PathBuf, sync::Arc, time::Duration};
 use tokio::time::sleep;
@@ -9,7 +9,7 @@ use watchexec::Watchexec;
 use watchexec_events::Tag;
 use watchexec_signals::Signal;
 
-use crate::errors::NoWay;
+use crate::{abstraction::PathFilter, errors::NoWay};
 
 #[derive(Setters, Default)]
 #[setters(strip_option)]
@@ -20,26 +20,29 @@ pub struct CodeWatch<T> {
     #[setters(skip)]
     dedup: Option<(Sender<Arc<PathBuf>>, Receiver<Arc<PathBuf>>)>,
 
-    #[setters(into)]
-    extension: Option<String>,
+    filter: Option<PathFilter>,
 
     queuer: Option<Sender<T>>,
 
+    /// How long to wait for further changes before delivering a batch, every change within
+    /// the window extends it
+    debounce: Option<Duration>,
+
     ctor: Option<WatcherCtor<T>>,
 }
 
-type WatcherCtor<T> = Arc<dyn Fn(Arc<PathBuf>) -> T + Send + Sync + 'static>;
+type WatcherCtor<T> = Arc<dyn Fn(Vec<Arc<PathBuf>>) -> T + Send + Sync + 'static>;
 
-impl<T: Send + Sync + 'static + Eq + std::hash::Hash> CodeWatch<T> {
+impl<T: Send + Sync + 'static> CodeWatch<T> {
     pub fn build(
         mut self,
-        path: &str,
-        ctor: impl Fn(Arc<PathBuf>) -> T + Send + Sync + 'static + Clone,
+        paths: &[String],
+        ctor: impl Fn(Vec<Arc<PathBuf>>) -> T + Send + Sync + 'static + Clone,
     ) -> anyhow::Result<Self> {
         self.dedup = Some(channel::<Arc<PathBuf>>(1024));
         self.ctor = Some(Arc::new(ctor));
 
-        let maybe_expected_extension = Arc::new(self.extension.clone());
+        let maybe_filter = Arc::new(self.filter.clone());
 
         let watcher = Watchexec::new({
             let dedup_queuer = self
@@ -49,7 +52,7 @@ impl<T: Send + Sync + 'static + Eq + std::hash::Hash> CodeWatch<T> {
                 .0
                 .clone();
 
-            let maybe_expected_extension = maybe_expected_extension.clone();
+            let maybe_filter = maybe_filter.clone();
 
             move |mut action| {
                 for event in action.events.iter() {
@@ -71,12 +74,8 @@ impl<T: Send + Sync + 'static + Eq + std::hash::Hash> CodeWatch<T> {
                                 continue;
                             }
 
-                            let Some(extension) = path.extension() else {
-                                continue;
-                            };
-
-                            if let Some(expected_extension) = maybe_expected_extension.as_ref() {
-                                if !extension.to_string_lossy().ends_with(expected_extension) {
+                            if let Some(filter) = maybe_filter.as_ref() {
+                                if !filter.matches(path) {
                                     continue;
                                 }
                             }
@@ -103,7 +102,7 @@ impl<T: Send + Sync + 'static + Eq + std::hash::Hash> CodeWatch<T> {
         })
         .context("An error occurred while building the file watcher")?;
 
-        watcher.config.pathset([path]);
+        watcher.config.pathset(paths.iter().map(String::as_str));
         self.watcher = Some(watcher);
 
         Ok(self)
@@ -122,46 +121,46 @@ impl<T: Send + Sync + 'static + Eq + std::hash::Hash> CodeWatch<T> {
                 .no_way_because("the codebase should have run `build` method first")
                 .1;
 
-            async move {
-                loop {
-                    let deadline = sleep(Duration::from_millis(16));
-
-                    let mut batch = Vec::with_capacity(1024);
-
-                    let batch_fut = dedup_queue
-                        .by_ref()
-                        .take(1024)
-                        .take_until(deadline)
-                        .collect::<Vec<_>>();
-
-                    pin_mut!(batch_fut);
+            let debounce = self.debounce.unwrap_or(Duration::from_millis(16));
 
-                    tokio::select! {
-                        mut rest = batch_fut => {
-                            batch.extend(rest.drain(..));
-                        }
+            async move {
+                'watch: loop {
+                    let first = tokio::select! {
+                        path = dedup_queue.next() => path,
                         _ = tokio::signal::ctrl_c() => {
                             eprintln!(" terminated watcher");
                             break;
                         }
+                    };
+
+                    let Some(first) = first else {
+                        break;
+                    };
+
+                    let mut batch = vec![first];
+
+                    loop {
+                        tokio::select! {
+                            path = dedup_queue.next() => match path {
+                                Some(path) => batch.push(path),
+                                None => break,
+                            },
+                            _ = sleep(debounce) => break,
+                            _ = tokio::signal::ctrl_c() => {
+                                eprintln!(" terminated watcher");
+                                break 'watch;
+                            }
+                        }
                     }
 
-                    if batch.is_empty() {
-                        continue;
-                    }
-
-                    let deduped = batch
-                        .into_iter()
-                        .map(|path| ctor(path))
-                        .collect::<HashSet<_>>();
+                    let mut seen = HashSet::new();
+                    batch.retain(|path| seen.insert(Arc::clone(path)));
 
                     if let Some(queuer) = &mut self.queuer {
-                        for item in deduped {
-                            queuer
-                                .send(item)
-                                .await
-                                .no_way_because("the receiver should still be alive by design");
-                        }
+                        queuer
+                            .send(ctor(batch))
+                            .await
+                            .no_way_because("the receiver should still be alive by design");
                     }
                 }
             }
@@ -29,7 +29,30 @@ pub async fn supabase() -> anyhow::Result<Vec<ContainerSummary>> {
         .collect())
 }
 
-pub async fn shadow_db() -> anyhow::Result<Option<ContainerSummary>> {
+/// Database container of the running project
+pub async fn local_db(project_id: &str) -> anyhow::Result<Option<ContainerSummary>> {
+    running(&format!("supabase_db_{}", project_id)).await
+}
+
+/// Running container of the given name
+pub async fn running(name: &str) -> anyhow::Result<Option<ContainerSummary>> {
+    let docker = Docker::connect_with_socket_defaults().with_context(|| NO_DOCKER.clone())?;
+    let name = format!("/{}", name);
+
+    Ok(docker
+        .list_containers(None::<ListContainersOptions>)
+        .await
+        .context("It failed to get list of containers, try restarting Docker, or reinstalling it if it doesn't help")?
+        .into_iter()
+        .find(|container| {
+            container
+                .names
+                .as_ref()
+                .is_some_and(|names| names.contains(&name))
+        }))
+}
+
+pub async fn shadow_db(port: u16) -> anyhow::Result<Option<ContainerSummary>> {
     let docker = Docker::connect_with_socket_defaults().with_context(|| NO_DOCKER.clone())?;
 
     let containers = docker
@@ -56,9 +79,9 @@ pub async fn shadow_db() -> anyhow::Result<Option<ContainerSummary>> {
             continue;
         };
 
-        let is_54320 = ports.iter().any(|item| item.public_port == Some(54320));
+        let is_shadow_port = ports.iter().any(|item| item.public_port == Some(port));
 
-        if !is_54320 {
+        if !is_shadow_port {
             continue;
         }
 
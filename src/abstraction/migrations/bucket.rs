@@ -5,34 +5,106 @@ use crate::patched::promptuity::{
     prompts::{Confirm, Input, Select, SelectOption},
 };
 use anyhow::Context;
+use heck::ToSnakeCase;
 
-use crate::abstraction::Migration;
+use crate::abstraction::{Migration, quote_literal, text_array};
+use crate::utils::parse_size;
 
 pub struct NewBucket {
     pub name: String,
     pub public: bool,
     pub mime_types: Vec<String>,
+    pub file_size_limit: Option<u64>,
+    pub avif_autodetection: bool,
+
+    /// Whether the migration should reconcile an already existing bucket instead of failing
+    pub upsert: bool,
+}
+
+/// Bucket values known upfront, e.g. passed as flags, missing ones are prompted for
+#[derive(Default)]
+pub struct BucketDraft {
+    pub name: Option<String>,
+    pub public: Option<bool>,
+    pub mime_types: Vec<String>,
+    pub file_size_limit: Option<u64>,
+    pub avif_autodetection: bool,
+    pub upsert: bool,
 }
 
 impl Migration for NewBucket {
     fn sql(&self) -> String {
-        format!(
-            r#"INSERT INTO storage.buckets (id, name, public, allowed_mime_types) VALUES ('{0}', '{0}', {1}, '{{{2}}}'::text[]);"#,
-            self.name,
+        let name = quote_literal(&self.name);
+
+        let mut sql = format!(
+            r#"INSERT INTO storage.buckets (id, name, public, file_size_limit, allowed_mime_types, avif_autodetection)
+VALUES ({name}, {name}, {}, {}, {}, {})"#,
             self.public,
-            self.mime_types.join(", ")
-        )
+            self.file_size_limit
+                .map(|limit| limit.to_string())
+                .unwrap_or("NULL".into()),
+            text_array(&self.mime_types),
+            self.avif_autodetection
+        );
+
+        if self.upsert {
+            sql.push_str(
+                r#"
+ON CONFLICT (id) DO UPDATE SET
+  name = excluded.name,
+  public = excluded.public,
+  file_size_limit = excluded.file_size_limit,
+  allowed_mime_types = excluded.allowed_mime_types,
+  avif_autodetection = excluded.avif_autodetection"#,
+            );
+        }
+
+        sql.push_str(";\n");
+        sql
     }
 
     fn migration_name(&self) -> String {
-        format!("create_{}_bucket", self.name)
+        format!("create_{}_bucket", self.name.to_snake_case())
     }
 }
 
 impl NewBucket {
+    pub fn guess_mime_type(extension: &str) -> Option<String> {
+        mime_guess::from_path(format!("_.{}", extension.trim_start_matches('.')))
+            .first()
+            .map(|mime_type| mime_type.to_string())
+    }
+
+    pub fn from_draft(draft: BucketDraft) -> anyhow::Result<Self> {
+        let Some(name) = draft.name else {
+            crate::styled_bail!(
+                "Bucket name is missing, pass it with `{}` or run the command in a terminal",
+                ("--name", "command")
+            );
+        };
+
+        let Some(public) = draft.public else {
+            crate::styled_bail!(
+                "Bucket visibility is missing, pass `{}` or `{}` or run the command in a terminal",
+                ("--public", "command"),
+                ("--private", "command")
+            );
+        };
+
+        Ok(Self {
+            name,
+            public,
+            mime_types: draft.mime_types,
+            file_size_limit: draft.file_size_limit,
+            avif_autodetection: draft.avif_autodetection,
+            upsert: draft.upsert,
+        })
+    }
+
     pub fn new_interactively(
         promptuity: &mut Promptuity<'_, Stderr>,
         project_id: &str,
+        draft: BucketDraft,
     ) -> anyhow::Result<Self> {
         let intro = format!("Creating bucket ({})", project_id);
 
@@ -41,72 +113,136 @@ impl NewBucket {
             .begin()
             .context("You don't seem to be in an interactive mode")?;
 
-        let name = promptuity
-            .prompt(
-                Input::new("Please enter a slug for your new bucket").with_placeholder("cabinets"),
-            )
-            .ok()
-            .context("Stopped")?;
-
-        let public = promptuity
-            .prompt(
-                Select::new(
-                    "Set the visibility of your bucket",
-                    vec![
-                        SelectOption::new("Public", true),
-                        SelectOption::new("Private", false),
-                    ],
+        let name = match draft.name {
+            Some(name) => name,
+            None => promptuity
+                .prompt(
+                    Input::new("Please enter a slug for your new bucket")
+                        .with_placeholder("cabinets"),
                 )
-                .with_page_size(2),
-            )
-            .context("Stopped")?;
-
-        let mime_type_limitation = promptuity
-            .prompt(
-                Confirm::new("Would you also like to limit accepted mime types?")
-                    .with_default(false),
-            )
-            .context("Stopped")?;
-
-        let mut mime_types: Vec<String> = vec![];
-
-        if mime_type_limitation {
-            loop {
-                let hint = format!("{}", mime_types.clone().join(", "));
-
-                let ext = promptuity
-                    .prompt(
-                        Input::new("Please enter a file extension and we will guess the mime type")
-                            .with_placeholder("jpg")
-                            .with_hint(&hint),
+                .ok()
+                .context("Stopped")?,
+        };
+
+        let public = match draft.public {
+            Some(public) => public,
+            None => promptuity
+                .prompt(
+                    Select::new(
+                        "Set the visibility of your bucket",
+                        vec![
+                            SelectOption::new("Public", true),
+                            SelectOption::new("Private", false),
+                        ],
                     )
-                    .context("Stopped")?;
-
-                let Some(mime_type) = mime_guess::from_path(format!("_.{}", ext)).first() else {
-                    continue;
-                };
+                    .with_page_size(2),
+                )
+                .context("Stopped")?,
+        };
+
+        let mut mime_types = draft.mime_types;
+        let mut file_size_limit = draft.file_size_limit;
+        let mut upsert = draft.upsert;
+
+        // Optional settings passed upfront aren't asked about again
+        if mime_types.is_empty() {
+            let mime_type_limitation = promptuity
+                .prompt(
+                    Confirm::new("Would you also like to limit accepted mime types?")
+                        .with_default(false),
+                )
+                .context("Stopped")?;
 
-                mime_types.push(mime_type.to_string());
+            if mime_type_limitation {
+                Self::prompt_mime_types(promptuity, &mut mime_types)?;
+            }
+        }
 
-                let hint = format!("[{}]", mime_types.clone().join(", "));
-                let will_add_more = promptuity
-                    .prompt(
-                        Confirm::new("Would you like to add another mime type?")
-                            .with_default(false)
-                            .with_hint(hint),
-                    )
-                    .context("Stopped")?;
+        if file_size_limit.is_none() {
+            let size_limitation = promptuity
+                .prompt(
+                    Confirm::new("Would you also like to limit the size of a single file?")
+                        .with_default(false),
+                )
+                .context("Stopped")?;
 
-                if !will_add_more {
-                    break;
-                }
+            if size_limitation {
+                file_size_limit = Some(Self::prompt_file_size_limit(promptuity)?);
             }
         }
 
+        if !upsert {
+            upsert = promptuity
+                .prompt(
+                    Confirm::new(
+                        "Should the migration update the bucket instead of failing if it already exists?",
+                    )
+                    .with_default(false),
+                )
+                .context("Stopped")?;
+        }
+
         Ok(Self {
             name,
             public,
             mime_types,
+            file_size_limit,
+            avif_autodetection: draft.avif_autodetection,
+            upsert,
         })
     }
+
+    /// Asks for file extensions to guess accepted mime types from, appending them to the list
+    pub fn prompt_mime_types(
+        promptuity: &mut Promptuity<'_, Stderr>,
+        mime_types: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        loop {
+            let hint = format!("{}", mime_types.clone().join(", "));
+
+            let ext = promptuity
+                .prompt(
+                    Input::new("Please enter a file extension and we will guess the mime type")
+                        .with_placeholder("jpg")
+                        .with_hint(&hint),
+                )
+                .context("Stopped")?;
+
+            let Some(mime_type) = Self::guess_mime_type(&ext) else {
+                continue;
+            };
+
+            mime_types.push(mime_type);
+
+            let hint = format!("[{}]", mime_types.clone().join(", "));
+            let will_add_more = promptuity
+                .prompt(
+                    Confirm::new("Would you like to add another mime type?")
+                        .with_default(false)
+                        .with_hint(hint),
+                )
+                .context("Stopped")?;
+
+            if !will_add_more {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn prompt_file_size_limit(promptuity: &mut Promptuity<'_, Stderr>) -> anyhow::Result<u64> {
+        let limit = promptuity
+            .prompt(
+                Input::new("Please enter the limit, in bytes or with a unit")
+                    .with_placeholder("5MB")
+                    .with_validator(|value: &String| {
+                        parse_size(value).map(|_| ()).ok_or(
+                            "Use a number optionally followed by a unit like KB, MB, GB, KiB, MiB, GiB"
+                                .into(),
+                        )
+                    }),
+            )
+            .context("Stopped")?;
+
+        parse_size(&limit).context("Invalid file size limit")
+    }
 }
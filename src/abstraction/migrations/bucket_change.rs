@@ -0,0 +1,239 @@
+use std::io::Stderr;
+
+use crate::patched::promptuity::{
+    Promptuity,
+    prompts::{Confirm, MultiSelect, MultiSelectOption, Select, SelectOption},
+};
+use anyhow::Context;
+use heck::ToSnakeCase;
+
+use crate::abstraction::{Migration, NewBucket, quote_literal, text_array};
+
+/// A bucket as currently stored in `"storage"."buckets"`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StorageBucket {
+    pub id: String,
+    pub public: bool,
+    pub mime_types: Vec<String>,
+    pub file_size_limit: Option<u64>,
+    pub avif_autodetection: bool,
+}
+
+impl StorageBucket {
+    pub fn summary(&self) -> String {
+        let mut parts = vec![if self.public { "public" } else { "private" }.to_string()];
+
+        if !self.mime_types.is_empty() {
+            parts.push(self.mime_types.join(", "));
+        }
+
+        if let Some(limit) = self.file_size_limit {
+            parts.push(format!("up to {limit} bytes"));
+        }
+
+        parts.join(" · ")
+    }
+}
+
+pub enum BucketChange {
+    Update {
+        before: StorageBucket,
+        after: StorageBucket,
+    },
+    Delete(StorageBucket),
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum BucketAction {
+    #[default]
+    ToggleVisibility,
+    EditMimeTypes,
+    EditFileSizeLimit,
+    Delete,
+}
+
+impl Migration for BucketChange {
+    fn sql(&self) -> String {
+        match self {
+            Self::Update { before, after } => {
+                let mut assignments = Vec::new();
+
+                if before.public != after.public {
+                    assignments.push(format!("public = {}", after.public));
+                }
+
+                if before.mime_types != after.mime_types {
+                    assignments.push(format!(
+                        "allowed_mime_types = {}",
+                        text_array(&after.mime_types)
+                    ));
+                }
+
+                if before.file_size_limit != after.file_size_limit {
+                    assignments.push(format!(
+                        "file_size_limit = {}",
+                        after
+                            .file_size_limit
+                            .map(|limit| limit.to_string())
+                            .unwrap_or("NULL".into())
+                    ));
+                }
+
+                if before.avif_autodetection != after.avif_autodetection {
+                    assignments.push(format!(
+                        "avif_autodetection = {}",
+                        after.avif_autodetection
+                    ));
+                }
+
+                format!(
+                    "UPDATE storage.buckets SET {} WHERE id = {};\n",
+                    assignments.join(", "),
+                    quote_literal(&after.id)
+                )
+            }
+            Self::Delete(bucket) => format!(
+                "DELETE FROM storage.buckets WHERE id = {};\n",
+                quote_literal(&bucket.id)
+            ),
+        }
+    }
+
+    fn migration_name(&self) -> String {
+        match self {
+            Self::Update { after, .. } => format!("update_{}_bucket", after.id.to_snake_case()),
+            Self::Delete(bucket) => format!("delete_{}_bucket", bucket.id.to_snake_case()),
+        }
+    }
+}
+
+impl BucketChange {
+    pub fn new_interactively(
+        promptuity: &mut Promptuity<'_, Stderr>,
+        buckets: Vec<StorageBucket>,
+        project_id: &str,
+    ) -> anyhow::Result<Self> {
+        promptuity
+            .with_intro(format!("Buckets ({})", project_id))
+            .begin()
+            .context("Failed to start interactive mode")?;
+
+        let page_size = buckets.len();
+
+        let before = promptuity
+            .prompt(
+                Select::new(
+                    "Which bucket would you like to change?",
+                    buckets
+                        .into_iter()
+                        .map(|bucket| {
+                            SelectOption::new(bucket.id.clone(), bucket.clone())
+                                .with_hint(bucket.summary())
+                        })
+                        .collect(),
+                )
+                .with_page_size(page_size.min(10)),
+            )
+            .context("Stopped")?;
+
+        let action = promptuity
+            .prompt(
+                Select::new(
+                    "What would you like to do with it?",
+                    vec![
+                        SelectOption::new(
+                            if before.public {
+                                "Make it private"
+                            } else {
+                                "Make it public"
+                            },
+                            BucketAction::ToggleVisibility,
+                        ),
+                        SelectOption::new("Edit accepted mime types", BucketAction::EditMimeTypes),
+                        SelectOption::new("Edit file size limit", BucketAction::EditFileSizeLimit),
+                        SelectOption::new("Delete it", BucketAction::Delete),
+                    ],
+                )
+                .with_page_size(4),
+            )
+            .context("Stopped")?;
+
+        let mut after = before.clone();
+
+        match action {
+            BucketAction::ToggleVisibility => after.public = !before.public,
+            BucketAction::EditMimeTypes => {
+                if !before.mime_types.is_empty() {
+                    after.mime_types = promptuity
+                        .prompt(
+                            MultiSelect::new(
+                                "Which mime types should stay accepted?",
+                                before
+                                    .mime_types
+                                    .iter()
+                                    .map(|mime_type| MultiSelectOption {
+                                        label: mime_type.clone(),
+                                        value: mime_type.clone(),
+                                        selected: true,
+                                        hint: None,
+                                    })
+                                    .collect(),
+                            )
+                            .with_required(false)
+                            .with_hint("deselect to stop accepting")
+                            .as_mut(),
+                        )
+                        .context("Stopped")?;
+                }
+
+                let will_add = promptuity
+                    .prompt(
+                        Confirm::new("Would you like to accept more mime types?")
+                            .with_default(before.mime_types.is_empty()),
+                    )
+                    .context("Stopped")?;
+
+                if will_add {
+                    NewBucket::prompt_mime_types(promptuity, &mut after.mime_types)?;
+                }
+            }
+            BucketAction::EditFileSizeLimit => {
+                let will_limit = promptuity
+                    .prompt(
+                        Confirm::new("Would you like to limit the size of a single file?")
+                            .with_default(true),
+                    )
+                    .context("Stopped")?;
+
+                after.file_size_limit = if will_limit {
+                    Some(NewBucket::prompt_file_size_limit(promptuity)?)
+                } else {
+                    None
+                };
+            }
+            BucketAction::Delete => {
+                let confirmed = promptuity
+                    .prompt(
+                        Confirm::new(&format!(
+                            "Are you sure you want to delete `{}`? It has to be empty for the migration to succeed",
+                            before.id
+                        ))
+                        .with_default(false),
+                    )
+                    .context("Stopped")?;
+
+                if !confirmed {
+                    crate::styled_bail!("No changes to apply")
+                }
+
+                return Ok(Self::Delete(before));
+            }
+        }
+
+        if before == after {
+            crate::styled_bail!("No changes to apply")
+        }
+
+        Ok(Self::Update { before, after })
+    }
+}
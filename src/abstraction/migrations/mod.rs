@@ -16,5 +16,11 @@ impl Migration for (String, String) {
 pub mod bucket;
 pub use bucket::*;
 
+pub mod bucket_change;
+pub use bucket_change::*;
+
 pub mod realtime;
 pub use realtime::*;
+
+pub mod storage_policy;
+pub use storage_policy::*;
@@ -1,29 +1,168 @@
+use std::fmt::Display;
 use std::io::Stderr;
 
 use crate::patched::promptuity::{
     Promptuity,
-    prompts::{MultiSelect, MultiSelectOption},
+    prompts::{Confirm, Input, MultiSelect, MultiSelectOption, Select, SelectOption},
 };
 use anyhow::Context;
+use heck::ToSnakeCase;
 
-use crate::abstraction::Migration;
+use crate::abstraction::{Migration, quote_ident};
+
+pub static REALTIME_PUBLICATION: &str = "supabase_realtime";
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TableRef {
+    pub schema: String,
+    pub name: String,
+}
+
+impl TableRef {
+    pub fn quoted(&self) -> String {
+        format!("{}.{}", quote_ident(&self.schema), quote_ident(&self.name))
+    }
+}
+
+impl Display for TableRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.schema, self.name)
+    }
+}
+
+/// Table membership in a publication, optionally limited to a column list and a row filter
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PublishedTable {
+    pub table: TableRef,
+    pub columns: Option<Vec<String>>,
+    pub row_filter: Option<String>,
+}
+
+impl PublishedTable {
+    pub fn whole(table: TableRef) -> Self {
+        Self {
+            table,
+            columns: None,
+            row_filter: None,
+        }
+    }
+
+    fn sql(&self) -> String {
+        let mut sql = self.table.quoted();
+
+        if let Some(columns) = &self.columns {
+            sql.push_str(&format!(
+                " ({})",
+                columns
+                    .iter()
+                    .map(|column| quote_ident(column))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        if let Some(row_filter) = &self.row_filter {
+            sql.push_str(&format!(" where ({row_filter})"));
+        }
+
+        sql
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        let mut parts = Vec::new();
+
+        if let Some(columns) = &self.columns {
+            parts.push(format!("columns: {}", columns.join(", ")));
+        }
+
+        if let Some(row_filter) = &self.row_filter {
+            parts.push(format!("where {row_filter}"));
+        }
+
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
+}
+
+/// `REPLICA IDENTITY` of a table, decides which old values end up in UPDATE/DELETE payloads
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum ReplicaIdentity {
+    /// Primary key columns only
+    #[default]
+    Default,
+
+    /// Every column, required for realtime to deliver complete old records
+    Full,
+
+    /// No old values at all
+    Nothing,
+
+    /// Columns of the given unique index
+    Index(String),
+}
+
+impl ReplicaIdentity {
+    /// Maps `pg_class.relreplident` along with the index marked with `indisreplident`
+    pub fn from_catalog(kind: &str, index: Option<String>) -> Self {
+        match (kind, index) {
+            ("f", _) => Self::Full,
+            ("n", _) => Self::Nothing,
+            ("i", Some(index)) => Self::Index(index),
+            _ => Self::Default,
+        }
+    }
+
+    fn sql(&self) -> String {
+        match self {
+            Self::Default => "default".into(),
+            Self::Full => "full".into(),
+            Self::Nothing => "nothing".into(),
+            Self::Index(index) => format!("using index {}", quote_ident(index)),
+        }
+    }
+}
+
+impl Display for ReplicaIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Full => write!(f, "full"),
+            Self::Nothing => write!(f, "nothing"),
+            Self::Index(index) => write!(f, "index {index}"),
+        }
+    }
+}
+
+/// Current replica identity of a table along with unique indexes it could be switched to
+#[derive(Debug, Default, Clone)]
+pub struct TableIdentity {
+    pub table: TableRef,
+    pub identity: ReplicaIdentity,
+    pub indexes: Vec<String>,
+}
 
 pub struct RealtimeChange {
-    schema: String,
-    to_add: Vec<String>,
-    to_remove: Vec<String>,
+    publication: String,
+    create_publication: bool,
+    to_add: Vec<PublishedTable>,
+    to_remove: Vec<TableRef>,
+    replica_identities: Vec<(TableRef, ReplicaIdentity)>,
 }
 
 impl Migration for RealtimeChange {
     fn sql(&self) -> String {
         let mut lines = Vec::new();
+        let publication = quote_ident(&self.publication);
 
-        if !self.to_add.is_empty() {
+        if self.create_publication {
+            lines.push(format!("create publication {publication};"));
+        }
+
+        if !self.to_remove.is_empty() {
             let line = format!(
-                r#"alter publication supabase_realtime add table {};"#,
-                self.to_add
+                r#"alter publication {publication} drop table {};"#,
+                self.to_remove
                     .iter()
-                    .map(|item| format!("{:?}.{item:?}", self.schema))
+                    .map(TableRef::quoted)
                     .collect::<Vec<String>>()
                     .join(", ")
             );
@@ -31,12 +170,12 @@ impl Migration for RealtimeChange {
             lines.push(line);
         }
 
-        if !self.to_remove.is_empty() {
+        if !self.to_add.is_empty() {
             let line = format!(
-                r#"alter publication supabase_realtime drop table {};"#,
-                self.to_remove
+                r#"alter publication {publication} add table {};"#,
+                self.to_add
                     .iter()
-                    .map(|item| format!("{:?}.{item:?}", self.schema))
+                    .map(PublishedTable::sql)
                     .collect::<Vec<String>>()
                     .join(", ")
             );
@@ -44,38 +183,77 @@ impl Migration for RealtimeChange {
             lines.push(line);
         }
 
+        for (table, identity) in &self.replica_identities {
+            lines.push(format!(
+                "alter table {} replica identity {};",
+                table.quoted(),
+                identity.sql()
+            ));
+        }
+
         lines.join("\n")
     }
 
     fn migration_name(&self) -> String {
-        format!("change_realtime")
+        if self.publication == REALTIME_PUBLICATION {
+            "change_realtime".to_string()
+        } else {
+            format!("change_{}_publication", self.publication.to_snake_case())
+        }
     }
 }
 
 impl RealtimeChange {
     pub fn new_interactively(
         promptuity: &mut Promptuity<'_, Stderr>,
-        schema: &str,
-        tables: Vec<String>,
-        enabled_for: Vec<String>,
+        publication: &str,
+        create_publication: bool,
+        tables: Vec<TableRef>,
+        published: Vec<PublishedTable>,
+        identities: Vec<TableIdentity>,
         project_id: &str,
     ) -> anyhow::Result<Self> {
+        let intro = if publication == REALTIME_PUBLICATION {
+            format!("Realtime ({})", project_id)
+        } else {
+            format!("Publication `{}` ({})", publication, project_id)
+        };
+
         promptuity
-            .with_intro(format!("Realtime ({})", project_id))
+            .with_intro(intro)
             .begin()
             .context("Failed to start interactive mode")?;
 
+        let published_for = |table: &TableRef| published.iter().find(|item| &item.table == table);
+        let identity_for = |table: &TableRef| identities.iter().find(|item| &item.table == table);
+
+        let hint_for = |table: &TableRef| {
+            let identity = identity_for(table)
+                .map(|item| format!("replica identity {}", item.identity));
+
+            match (published_for(table).and_then(PublishedTable::hint), identity) {
+                (Some(hint), Some(identity)) => Some(format!("{hint} · {identity}")),
+                (hint, identity) => hint.or(identity),
+            }
+        };
+
+        let question = if publication == REALTIME_PUBLICATION {
+            "Which tables do you want to have realtime enabled for?"
+        } else {
+            "Which tables do you want to have published?"
+        };
+
         let matrix = promptuity
             .prompt(
                 MultiSelect::new(
-                    "Which tables do you want to have realtime enabled for?",
+                    question,
                     tables
                         .iter()
                         .map(|table| MultiSelectOption {
-                            label: table.clone(),
+                            label: table.to_string(),
                             value: table.clone(),
-                            selected: enabled_for.contains(table),
-                            hint: None,
+                            selected: published_for(table).is_some(),
+                            hint: hint_for(table),
                         })
                         .collect(),
                 )
@@ -85,29 +263,255 @@ impl RealtimeChange {
             )
             .context("Stopped")?;
 
-        let mut to_add = Vec::new();
-        let mut to_remove = Vec::new();
+        let will_restrict = !matrix.is_empty()
+            && promptuity
+                .prompt(
+                    Confirm::new(
+                        "Would you like to limit columns or filter rows published for any of them?",
+                    )
+                    .with_default(false),
+                )
+                .context("Stopped")?;
+
+        let mut restricted = Vec::<PublishedTable>::new();
+
+        if will_restrict {
+            let selected = promptuity
+                .prompt(
+                    MultiSelect::new(
+                        "Which tables should be limited?",
+                        matrix
+                            .iter()
+                            .map(|table| MultiSelectOption {
+                                label: table.to_string(),
+                                value: table.clone(),
+                                selected: false,
+                                hint: published_for(table).and_then(PublishedTable::hint),
+                            })
+                            .collect(),
+                    )
+                    .with_required(false)
+                    .as_mut(),
+                )
+                .context("Stopped")?;
+
+            for table in selected {
+                let columns = promptuity
+                    .prompt(
+                        Input::new(format!("Columns of `{table}` to publish"))
+                            .with_placeholder("id, status")
+                            .with_hint("comma separated, leave empty for all")
+                            .with_required(false),
+                    )
+                    .context("Stopped")?;
 
-        for item in &matrix {
-            if !enabled_for.contains(&item) {
-                to_add.push(item.clone());
+                let row_filter = promptuity
+                    .prompt(
+                        Input::new(format!("Rows of `{table}` to publish"))
+                            .with_placeholder("status <> 'draft'")
+                            .with_hint("a `where` condition, leave empty for all")
+                            .with_required(false),
+                    )
+                    .context("Stopped")?;
+
+                let columns = columns
+                    .split(',')
+                    .map(|column| column.trim().to_string())
+                    .filter(|column| !column.is_empty())
+                    .collect::<Vec<_>>();
+
+                restricted.push(PublishedTable {
+                    table,
+                    columns: (!columns.is_empty()).then_some(columns),
+                    row_filter: Some(row_filter.trim().to_string())
+                        .filter(|row_filter| !row_filter.is_empty()),
+                });
             }
         }
 
-        for item in enabled_for {
-            if !matrix.contains(&item) {
-                to_remove.push(item.clone());
+        let desired: Vec<PublishedTable> = matrix
+            .into_iter()
+            .map(|table| {
+                restricted
+                    .iter()
+                    .find(|item| item.table == table)
+                    .cloned()
+                    .or_else(|| published_for(&table).cloned())
+                    .unwrap_or_else(|| PublishedTable::whole(table))
+            })
+            .collect();
+
+        let replica_identities = Self::prompt_replica_identities(
+            promptuity,
+            desired.iter().map(|item| &item.table),
+            &identities,
+        )?;
+
+        let mut result = Self::diff(publication, create_publication, published, desired);
+        result.replica_identities = replica_identities;
+
+        if result.is_empty() {
+            crate::styled_bail!("No changes to apply")
+        }
+
+        Ok(result)
+    }
+
+    /// Asks whether and how to switch replica identity of the given tables, only returning
+    /// the ones that actually change
+    fn prompt_replica_identities<'a>(
+        promptuity: &mut Promptuity<'_, Stderr>,
+        tables: impl Iterator<Item = &'a TableRef>,
+        identities: &[TableIdentity],
+    ) -> anyhow::Result<Vec<(TableRef, ReplicaIdentity)>> {
+        let candidates = tables
+            .filter_map(|table| identities.iter().find(|item| &item.table == table))
+            .collect::<Vec<_>>();
+
+        if candidates.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let will_change = promptuity
+            .prompt(
+                Confirm::new("Would you like to change replica identity of any of them?")
+                    .with_hint("`full` is needed for old values in UPDATE/DELETE payloads")
+                    .with_default(
+                        candidates
+                            .iter()
+                            .any(|item| item.identity != ReplicaIdentity::Full),
+                    ),
+            )
+            .context("Stopped")?;
+
+        if !will_change {
+            return Ok(Vec::new());
+        }
+
+        let selected = promptuity
+            .prompt(
+                MultiSelect::new(
+                    "Which tables should change replica identity?",
+                    candidates
+                        .iter()
+                        .map(|item| MultiSelectOption {
+                            label: item.table.to_string(),
+                            value: item.table.clone(),
+                            selected: item.identity != ReplicaIdentity::Full,
+                            hint: Some(item.identity.to_string()),
+                        })
+                        .collect(),
+                )
+                .with_required(false)
+                .as_mut(),
+            )
+            .context("Stopped")?;
+
+        let mut changes = Vec::new();
+
+        for item in candidates
+            .into_iter()
+            .filter(|item| selected.contains(&item.table))
+        {
+            let mut options = vec![
+                SelectOption::new("Default", ReplicaIdentity::Default)
+                    .with_hint("primary key columns"),
+                SelectOption::new("Full", ReplicaIdentity::Full).with_hint("all columns"),
+            ];
+
+            options.extend(item.indexes.iter().map(|index| {
+                SelectOption::new(
+                    format!("Index `{index}`"),
+                    ReplicaIdentity::Index(index.clone()),
+                )
+                .with_hint("columns of a unique index")
+            }));
+
+            let page_size = options.len().min(10);
+
+            let identity = promptuity
+                .prompt(
+                    Select::new(
+                        format!("Replica identity of `{}` (currently {})", item.table, item.identity),
+                        options,
+                    )
+                    .with_page_size(page_size),
+                )
+                .context("Stopped")?;
+
+            if identity != item.identity {
+                changes.push((item.table.clone(), identity));
             }
         }
 
-        if to_add.is_empty() && to_remove.is_empty() {
-            crate::styled_bail!("No changes to apply")
+        Ok(changes)
+    }
+
+    /// Computes the change enabling and disabling given tables without prompting, tables that
+    /// are already published keep their column list and row filter
+    pub fn toggle(
+        publication: &str,
+        create_publication: bool,
+        published: Vec<PublishedTable>,
+        enable: &[TableRef],
+        disable: &[TableRef],
+    ) -> Self {
+        let mut desired = published
+            .iter()
+            .filter(|item| !disable.contains(&item.table))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for table in enable {
+            if !desired.iter().any(|item| &item.table == table) {
+                desired.push(PublishedTable::whole(table.clone()));
+            }
+        }
+
+        Self::diff(publication, create_publication, published, desired)
+    }
+
+    /// Computes the change turning `published` into `desired`, tables whose column list or
+    /// row filter changes are dropped and added back
+    pub fn diff(
+        publication: &str,
+        create_publication: bool,
+        published: Vec<PublishedTable>,
+        desired: Vec<PublishedTable>,
+    ) -> Self {
+        let mut to_add = Vec::new();
+        let mut to_remove = Vec::new();
+
+        for item in &desired {
+            match published.iter().find(|current| current.table == item.table) {
+                Some(current) if current == item => {}
+                Some(current) => {
+                    to_remove.push(current.table.clone());
+                    to_add.push(item.clone());
+                }
+                None => to_add.push(item.clone()),
+            }
+        }
+
+        for item in published {
+            if !desired.iter().any(|wanted| wanted.table == item.table) {
+                to_remove.push(item.table);
+            }
         }
 
-        Ok(Self {
-            schema: schema.to_string(),
+        Self {
+            publication: publication.to_string(),
+            create_publication,
             to_add,
             to_remove,
-        })
+            replica_identities: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.create_publication
+            && self.to_add.is_empty()
+            && self.to_remove.is_empty()
+            && self.replica_identities.is_empty()
     }
 }
@@ -0,0 +1,258 @@
+use std::io::Stderr;
+
+use crate::patched::promptuity::{
+    Promptuity,
+    prompts::{Input, MultiSelect, MultiSelectOption, Select, SelectOption},
+};
+use anyhow::Context;
+use heck::ToSnakeCase;
+
+use crate::abstraction::{Migration, checksum, quote_ident, quote_literal};
+
+/// Longest identifier Postgres keeps, in bytes, longer ones are silently truncated
+static IDENTIFIER_LIMIT: usize = 63;
+
+/// Length of the hash suffix telling apart policies whose names had to be shortened
+static HASH_LENGTH: usize = 8;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, strum_macros::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum PolicyOperation {
+    #[default]
+    Select,
+    Insert,
+    Update,
+    Delete,
+}
+
+/// Which objects of the bucket the policy applies to
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum PathTemplate {
+    /// Objects under a top-level folder named after the user's id, i.e. `<auth.uid()>/…`
+    #[default]
+    OwnerFolder,
+
+    /// Every object in the bucket
+    WholeBucket,
+
+    /// Objects under a fixed folder
+    Folder(String),
+}
+
+impl PathTemplate {
+    fn slug(&self) -> String {
+        match self {
+            Self::OwnerFolder => "own folder".into(),
+            Self::WholeBucket => "all".into(),
+            Self::Folder(folder) => format!("in {folder}"),
+        }
+    }
+
+    fn condition(&self, bucket: &str) -> String {
+        let bucket = format!("bucket_id = {}", quote_literal(bucket));
+
+        match self {
+            Self::OwnerFolder => {
+                format!("{bucket} and (storage.foldername(name))[1] = (select auth.uid()::text)")
+            }
+            Self::WholeBucket => bucket,
+            Self::Folder(folder) => {
+                let escaped = folder
+                    .trim_matches('/')
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+
+                format!("{bucket} and name like {}", quote_literal(&format!("{escaped}/%")))
+            }
+        }
+    }
+}
+
+/// Set of `"storage"."objects"` row level security policies scoped to a single bucket
+pub struct NewStoragePolicy {
+    pub bucket: String,
+    pub operations: Vec<PolicyOperation>,
+
+    /// One of the built-in roles: `authenticated`, `anon` or `public`, left unquoted
+    pub role: String,
+    pub template: PathTemplate,
+}
+
+impl Migration for NewStoragePolicy {
+    fn sql(&self) -> String {
+        let condition = self.template.condition(&self.bucket);
+
+        self.operations
+            .iter()
+            .map(|operation| {
+                let name = quote_ident(&self.name(*operation));
+
+                let clauses = match operation {
+                    PolicyOperation::Select | PolicyOperation::Delete => {
+                        format!("using ({condition})")
+                    }
+                    PolicyOperation::Insert => format!("with check ({condition})"),
+                    PolicyOperation::Update => {
+                        format!("using ({condition})\n  with check ({condition})")
+                    }
+                };
+
+                format!(
+                    "drop policy if exists {name} on storage.objects;\ncreate policy {name} on storage.objects\n  for {} to {}\n  {};\n",
+                    operation,
+                    self.role,
+                    clauses
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn migration_name(&self) -> String {
+        format!("create_{}_storage_policies", self.bucket.to_snake_case())
+    }
+}
+
+impl NewStoragePolicy {
+    /// Name of the policy for the operation, ones not fitting Postgres' identifier limit are
+    /// shortened and suffixed with a hash of the full name, so they stay distinct
+    fn name(&self, operation: PolicyOperation) -> String {
+        let name = format!(
+            "{} {} {} {}",
+            self.bucket,
+            self.role,
+            operation,
+            self.template.slug()
+        );
+
+        if name.len() <= IDENTIFIER_LIMIT {
+            return name;
+        }
+
+        let prefix = &name[..name.floor_char_boundary(IDENTIFIER_LIMIT - HASH_LENGTH - 1)];
+
+        format!(
+            "{} {}",
+            prefix.trim_end(),
+            &checksum(&name)[..HASH_LENGTH]
+        )
+    }
+
+    pub fn new_interactively(
+        promptuity: &mut Promptuity<'_, Stderr>,
+        buckets: Vec<String>,
+        bucket: Option<String>,
+        project_id: &str,
+    ) -> anyhow::Result<Self> {
+        promptuity
+            .with_intro(format!("Storage policies ({})", project_id))
+            .begin()
+            .context("Failed to start interactive mode")?;
+
+        let bucket = match bucket {
+            Some(bucket) => bucket,
+            None => {
+                let page_size = buckets.len().min(10);
+
+                promptuity
+                    .prompt(
+                        Select::new(
+                            "Which bucket should the policies apply to?",
+                            buckets
+                                .into_iter()
+                                .map(|bucket| SelectOption::new(bucket.clone(), bucket))
+                                .collect(),
+                        )
+                        .with_page_size(page_size),
+                    )
+                    .context("Stopped")?
+            }
+        };
+
+        Self::prompt_for_bucket(promptuity, bucket)
+    }
+
+    /// Asks for everything but the bucket, to be used within an already started session
+    pub fn prompt_for_bucket(
+        promptuity: &mut Promptuity<'_, Stderr>,
+        bucket: String,
+    ) -> anyhow::Result<Self> {
+        let operations = promptuity
+            .prompt(
+                MultiSelect::new(
+                    "Which operations should be allowed?",
+                    [
+                        PolicyOperation::Select,
+                        PolicyOperation::Insert,
+                        PolicyOperation::Update,
+                        PolicyOperation::Delete,
+                    ]
+                    .into_iter()
+                    .map(|operation| MultiSelectOption {
+                        label: operation.to_string(),
+                        value: operation,
+                        selected: operation == PolicyOperation::Select,
+                        hint: None,
+                    })
+                    .collect(),
+                )
+                .with_hint("a separate policy is created for each")
+                .as_mut(),
+            )
+            .context("Stopped")?;
+
+        let role = promptuity
+            .prompt(
+                Select::new(
+                    "Who should they be allowed for?",
+                    vec![
+                        SelectOption::new("Signed in users", "authenticated".to_string())
+                            .with_hint("authenticated"),
+                        SelectOption::new("Anonymous users", "anon".to_string())
+                            .with_hint("anon"),
+                        SelectOption::new("Everyone", "public".to_string()).with_hint("public"),
+                    ],
+                )
+                .with_page_size(3),
+            )
+            .context("Stopped")?;
+
+        let template = promptuity
+            .prompt(
+                Select::new(
+                    "Which objects should they apply to?",
+                    vec![
+                        SelectOption::new("User's own folder", PathTemplate::OwnerFolder)
+                            .with_hint("<user id>/…"),
+                        SelectOption::new("Whole bucket", PathTemplate::WholeBucket),
+                        SelectOption::new(
+                            "A fixed folder",
+                            PathTemplate::Folder(String::new()),
+                        )
+                        .with_hint("<folder>/…"),
+                    ],
+                )
+                .with_page_size(3),
+            )
+            .context("Stopped")?;
+
+        let template = match template {
+            PathTemplate::Folder(_) => PathTemplate::Folder(
+                promptuity
+                    .prompt(
+                        Input::new("Please enter the folder path").with_placeholder("public"),
+                    )
+                    .context("Stopped")?,
+            ),
+            other => other,
+        };
+
+        Ok(Self {
+            bucket,
+            operations,
+            role,
+            template,
+        })
+    }
+}
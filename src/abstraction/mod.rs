@@ -9,3 +9,27 @@ pub use migrations::*;
 
 pub mod containers;
 pub use containers::*;
+
+pub mod sql;
+pub use sql::*;
+
+pub mod risk;
+pub use risk::*;
+
+pub mod catalog;
+pub use catalog::*;
+
+pub mod autodrop;
+pub use autodrop::*;
+
+pub mod sql_file;
+pub use sql_file::*;
+
+pub mod watch_state;
+pub use watch_state::*;
+
+pub mod path_filter;
+pub use path_filter::*;
+
+pub mod template;
+pub use template::*;
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use glob::{MatchOptions, Pattern};
+
+static MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: false,
+    require_literal_leading_dot: false,
+};
+
+/// Selects files under a set of watched paths by glob patterns matched against the path
+/// relative to the watched one, `*` matching across directories, e.g. `*.draft.sql`
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    /// Watched paths as passed along with their canonical form, used for absolute event paths
+    roots: Vec<(PathBuf, Option<PathBuf>)>,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    pub fn new(roots: &[String], include: &[String], exclude: &[String]) -> anyhow::Result<Self> {
+        let patterns = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    Pattern::new(pattern).with_context(|| {
+                        styled_error!("Invalid glob pattern `{}`", (pattern, "id"))
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+
+        Ok(Self {
+            roots: roots
+                .iter()
+                .map(|root| {
+                    let root = PathBuf::from(root);
+                    let canonical = std::fs::canonicalize(&root).ok();
+
+                    (root, canonical)
+                })
+                .collect(),
+            include: patterns(include)?,
+            exclude: patterns(exclude)?,
+        })
+    }
+
+    pub fn roots(&self) -> Vec<String> {
+        self.roots
+            .iter()
+            .map(|(root, _)| root.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Path relative to the watched path it's under, the file name for watched files
+    fn relative(&self, path: &Path) -> Option<PathBuf> {
+        let canonical = std::fs::canonicalize(path).ok();
+
+        self.roots.iter().find_map(|(root, canonical_root)| {
+            let relative = path.strip_prefix(root).ok().or_else(|| {
+                canonical
+                    .as_ref()?
+                    .strip_prefix(canonical_root.as_ref()?)
+                    .ok()
+            })?;
+
+            if relative.as_os_str().is_empty() {
+                return path.file_name().map(PathBuf::from);
+            }
+
+            Some(relative.to_path_buf())
+        })
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let Some(relative) = self.relative(path) else {
+            return false;
+        };
+
+        self.include
+            .iter()
+            .any(|pattern| pattern.matches_path_with(&relative, MATCH_OPTIONS))
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| pattern.matches_path_with(&relative, MATCH_OPTIONS))
+    }
+
+    /// Every matching file under the watched paths, in glob order, files under several
+    /// (overlapping) watched paths are listed once
+    pub fn files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut seen = HashSet::new();
+        let mut push = |path: PathBuf| {
+            let canonical = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+
+            if seen.insert(canonical) {
+                files.push(path);
+            }
+        };
+
+        for (root, _) in &self.roots {
+            if root.is_file() {
+                if self.matches(root) {
+                    push(root.clone());
+                }
+
+                continue;
+            }
+
+            let pattern = format!(
+                "{}/**/*",
+                Pattern::escape(root.to_string_lossy().trim_end_matches('/'))
+            );
+
+            glob::glob(&pattern)
+                .context("Invalid directory path passed")?
+                .filter_map(Result::ok)
+                .filter(|path| path.is_file() && self.matches(path))
+                .for_each(&mut push);
+        }
+
+        Ok(files)
+    }
+}
@@ -0,0 +1,168 @@
+use regex::Regex;
+
+use crate::{abstraction::SqlStatement, errors::NoWay};
+
+/// How risky running a statement against a database with data is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, strum_macros::Display)]
+pub enum Risk {
+    #[strum(serialize = "safe")]
+    Safe,
+
+    /// Fails unless existing rows satisfy it, they may need to be backfilled beforehand
+    #[strum(serialize = "requires backfill")]
+    RequiresBackfill,
+
+    /// Loses data
+    #[strum(serialize = "destructive")]
+    Destructive,
+}
+
+lazy_static::lazy_static! {
+    static ref DESTRUCTIVE: Vec<(Regex, &'static str)> = vec![
+        (
+            Regex::new(r"(?is)^\s*drop\s+table\b")
+                .no_way_because("the pattern is a valid regex"),
+            "drops a table",
+        ),
+        (
+            Regex::new(r"(?is)^\s*drop\s+schema\b")
+                .no_way_because("the pattern is a valid regex"),
+            "drops a schema",
+        ),
+        (
+            Regex::new(r"(?is)^\s*drop\s+sequence\b")
+                .no_way_because("the pattern is a valid regex"),
+            "drops a sequence",
+        ),
+        (
+            Regex::new(r"(?is)^\s*truncate\b")
+                .no_way_because("the pattern is a valid regex"),
+            "truncates a table",
+        ),
+        (
+            Regex::new(r"(?is)^\s*delete\s+from\b")
+                .no_way_because("the pattern is a valid regex"),
+            "deletes rows",
+        ),
+        (
+            Regex::new(r"(?is)^\s*alter\s+table\b.*\bdrop\s+column\b")
+                .no_way_because("the pattern is a valid regex"),
+            "drops a column",
+        ),
+        (
+            Regex::new(r"(?is)^\s*alter\s+table\b.*\balter\s+column\s+\S+\s+(?:set\s+data\s+)?type\b")
+                .no_way_because("the pattern is a valid regex"),
+            "changes a column type",
+        ),
+    ];
+
+    static ref BACKFILL: Vec<(Regex, &'static str)> = vec![
+        (
+            Regex::new(r"(?is)^\s*alter\s+table\b.*\balter\s+column\s+\S+\s+set\s+not\s+null\b")
+                .no_way_because("the pattern is a valid regex"),
+            "makes a column not null",
+        ),
+        (
+            Regex::new(r"(?is)^\s*alter\s+table\b.*\bvalidate\s+constraint\b")
+                .no_way_because("the pattern is a valid regex"),
+            "validates a constraint",
+        ),
+        (
+            Regex::new(r"(?is)^\s*create\s+unique\s+index\b")
+                .no_way_because("the pattern is a valid regex"),
+            "adds a unique index",
+        ),
+    ];
+
+    static ref ADD_COLUMN: Regex =
+        Regex::new(r"(?is)^\s*(?:alter\s+table\b.*?\b)?add\s+column\s+(.*)$")
+            .no_way_because("the pattern is a valid regex");
+    static ref ADD_CONSTRAINT: Regex =
+        Regex::new(r"(?is)^\s*alter\s+table\b.*\badd\s+constraint\b")
+            .no_way_because("the pattern is a valid regex");
+    static ref NOT_NULL: Regex =
+        Regex::new(r"(?is)\bnot\s+null\b").no_way_because("the pattern is a valid regex");
+    static ref FILLED: Regex = Regex::new(r"(?is)\b(?:default|generated)\b")
+        .no_way_because("the pattern is a valid regex");
+    static ref NOT_VALID: Regex =
+        Regex::new(r"(?is)\bnot\s+valid\b").no_way_because("the pattern is a valid regex");
+}
+
+impl SqlStatement {
+    /// Classifies the statement along with the reason, `None` for safe ones
+    pub fn risk(&self) -> (Risk, Option<&'static str>) {
+        let code = self.code();
+
+        let matching = |rules: &[(Regex, &'static str)]| {
+            rules
+                .iter()
+                .find(|(pattern, _)| pattern.is_match(&code))
+                .map(|(_, reason)| *reason)
+        };
+
+        if let Some(reason) = matching(&DESTRUCTIVE) {
+            return (Risk::Destructive, Some(reason));
+        }
+
+        if let Some(reason) = matching(&BACKFILL) {
+            return (Risk::RequiresBackfill, Some(reason));
+        }
+
+        // Column definitions are told apart by the statement's top-level commas, as types like
+        // `numeric(10,2)` have commas of their own
+        let adds_required_column = self.clauses().iter().any(|clause| {
+            ADD_COLUMN.captures(clause).is_some_and(|captures| {
+                NOT_NULL.is_match(&captures[1]) && !FILLED.is_match(&captures[1])
+            })
+        });
+
+        if adds_required_column {
+            return (
+                Risk::RequiresBackfill,
+                Some("adds a not null column without a default"),
+            );
+        }
+
+        if ADD_CONSTRAINT.is_match(&code) && !NOT_VALID.is_match(&code) {
+            return (Risk::RequiresBackfill, Some("adds a constraint"));
+        }
+
+        (Risk::Safe, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abstraction::split_statements;
+
+    fn risk(sql: &str) -> Risk {
+        split_statements(sql)
+            .first()
+            .map(|statement| statement.risk().0)
+            .no_way_because("the script has a statement")
+    }
+
+    #[test]
+    fn required_columns_of_types_with_modifiers_need_backfill() {
+        assert_eq!(
+            risk("alter table shop.items add column price numeric(10,2) not null;"),
+            Risk::RequiresBackfill
+        );
+
+        assert_eq!(
+            risk("alter table shop.items add column price numeric(10, 2) not null default 0;"),
+            Risk::Safe
+        );
+
+        assert_eq!(
+            risk("alter table shop.items add column price numeric(10,2), add column sku text not null;"),
+            Risk::RequiresBackfill
+        );
+
+        assert_eq!(
+            risk("alter table shop.items add column note varchar(20) default 'a,b', add column kept int;"),
+            Risk::Safe
+        );
+    }
+}
@@ -0,0 +1,580 @@
+use std::fmt::Display;
+
+use regex::Regex;
+
+use crate::errors::NoWay;
+
+/// Quotes an identifier (e.g. a schema or table name) the way Postgres' `quote_ident` does,
+/// but unconditionally
+pub fn quote_ident(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// Quotes a string literal the way Postgres' `quote_literal` does, relying on
+/// `standard_conforming_strings` being on (the default) so backslashes need no escaping
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Renders a `text[]` literal, `NULL` for an empty list
+pub fn text_array(values: &[String]) -> String {
+    if values.is_empty() {
+        return "NULL".into();
+    }
+
+    format!(
+        "ARRAY[{}]::text[]",
+        values
+            .iter()
+            .map(|value| quote_literal(value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Single statement of a script, without the trailing semicolon
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlStatement {
+    pub text: String,
+
+    /// Byte offset of the statement's first character within the script
+    pub offset: usize,
+}
+
+lazy_static::lazy_static! {
+    static ref NO_TRANSACTION: Regex = Regex::new(
+        r"(?is)^\s*(?:(?:create|drop)\s+(?:unique\s+)?index\s+concurrently|reindex\b.*\bconcurrently|vacuum|alter\s+system|(?:create|drop)\s+(?:database|tablespace)|(?:begin|start\s+transaction|commit|rollback|end|abort)\s*$)"
+    )
+    .no_way_because("the pattern is a valid regex");
+
+    static ref QUALIFIER: Regex = Regex::new(
+        r#"(?:"((?:[^"]|"")+)"|([A-Za-z_][\w$]*))\s*\.\s*(?:"|[A-Za-z_])"#
+    )
+    .no_way_because("the pattern is a valid regex");
+}
+
+impl SqlStatement {
+    /// Whether the statement can't run inside a transaction block, or manages one by itself
+    pub fn requires_no_transaction(&self) -> bool {
+        NO_TRANSACTION.is_match(&self.code())
+    }
+
+    /// Line and column (both 1-based) within the script of the character at 1-based
+    /// `position` within the statement, as reported by Postgres
+    pub fn locate(&self, script: &str, position: usize) -> (usize, usize) {
+        line_and_column(script, self.script_offset(script, position))
+    }
+
+    /// Byte offset within the script of the character at 1-based `position` within the
+    /// statement
+    pub fn script_offset(&self, script: &str, position: usize) -> usize {
+        let within = self
+            .text
+            .char_indices()
+            .nth(position.saturating_sub(1))
+            .map(|(index, _)| index)
+            .unwrap_or(self.text.len());
+
+        (self.offset + within).min(script.len())
+    }
+
+    /// Schemas (or tables, for qualified column names) qualifying names the statement
+    /// refers to, in order of appearance
+    pub fn qualifiers(&self) -> Vec<String> {
+        QUALIFIER
+            .captures_iter(&self.code())
+            .filter_map(|captures| match (captures.get(1), captures.get(2)) {
+                (Some(quoted), _) => Some(quoted.as_str().replace("\"\"", "\"")),
+                (_, Some(plain)) => Some(plain.as_str().to_lowercase()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Statement text with comments blanked out, useful for matching keywords
+    pub fn code(&self) -> String {
+        let mut code = String::with_capacity(self.text.len());
+
+        scan(&self.text, |chunk, kind| match kind {
+            Chunk::Comment => code.push_str(&" ".repeat(chunk.len())),
+            _ => code.push_str(chunk),
+        });
+
+        code
+    }
+
+    /// Code of the statement split on commas outside of parentheses, quotes and comments, e.g.
+    /// into the actions of an `alter table`
+    pub fn clauses(&self) -> Vec<String> {
+        let mut clauses = Vec::new();
+        let mut clause = String::new();
+        let mut depth = 0usize;
+
+        scan(&self.text, |chunk, kind| match kind {
+            Chunk::Comment => clause.push_str(&" ".repeat(chunk.len())),
+            Chunk::Quoted => clause.push_str(chunk),
+            Chunk::Code => {
+                for char in chunk.chars() {
+                    match char {
+                        ',' if depth == 0 => clauses.push(std::mem::take(&mut clause)),
+                        '(' => {
+                            depth += 1;
+                            clause.push(char);
+                        }
+                        ')' => {
+                            depth = depth.saturating_sub(1);
+                            clause.push(char);
+                        }
+                        _ => clause.push(char),
+                    }
+                }
+            }
+        });
+
+        clauses.push(clause);
+        clauses
+    }
+}
+
+/// Line and column (both 1-based) of the character at byte `offset` within the script
+fn line_and_column(script: &str, offset: usize) -> (usize, usize) {
+    let before = &script[..script.floor_char_boundary(offset.min(script.len()))];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map(|line| line.chars().count())
+        .unwrap_or_default()
+        + 1;
+
+    (line, column)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chunk {
+    Code,
+    Comment,
+    Quoted,
+}
+
+/// Walks the script calling `visit` with consecutive chunks, each either plain code, a comment
+/// or a quoted literal/identifier/dollar-quoted body, semicolons are always a separate chunk
+fn scan(sql: &str, mut visit: impl FnMut(&str, Chunk)) {
+    let bytes = sql.as_bytes();
+    let mut index = 0;
+
+    let is_ident = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80;
+
+    while index < bytes.len() {
+        let start = index;
+        let rest = &sql[index..];
+
+        let kind = if rest.starts_with("--") {
+            index += rest.find('\n').unwrap_or(rest.len());
+            Chunk::Comment
+        } else if rest.starts_with("/*") {
+            let mut depth = 0;
+
+            while index < bytes.len() {
+                if bytes[index..].starts_with(b"/*") {
+                    depth += 1;
+                    index += 2;
+                } else if bytes[index..].starts_with(b"*/") {
+                    depth -= 1;
+                    index += 2;
+
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    index += 1;
+                }
+            }
+
+            Chunk::Comment
+        } else if bytes[index] == b'\'' || bytes[index] == b'"' {
+            let quote = bytes[index];
+            let escapes = quote == b'\''
+                && index > 0
+                && bytes[index - 1].eq_ignore_ascii_case(&b'e')
+                && (index < 2 || !is_ident(bytes[index - 2]));
+
+            index += 1;
+
+            while index < bytes.len() {
+                if escapes && bytes[index] == b'\\' {
+                    index += 2;
+                } else if bytes[index] == quote {
+                    index += 1;
+
+                    if bytes.get(index) != Some(&quote) {
+                        break;
+                    }
+
+                    index += 1;
+                } else {
+                    index += 1;
+                }
+            }
+
+            Chunk::Quoted
+        } else if let Some(tag) = dollar_tag(rest).filter(|_| index == 0 || !is_ident(bytes[index - 1])) {
+            index += tag.len();
+            index += sql[index..]
+                .find(tag)
+                .map(|end| end + tag.len())
+                .unwrap_or(sql.len() - index);
+
+            Chunk::Quoted
+        } else if bytes[index] == b';' {
+            index += 1;
+            Chunk::Code
+        } else {
+            index += 1;
+
+            while index < bytes.len()
+                && !matches!(bytes[index], b'-' | b'/' | b'\'' | b'"' | b'$' | b';')
+            {
+                index += 1;
+            }
+
+            Chunk::Code
+        };
+
+        let index_at_boundary = sql.ceil_char_boundary(index.min(sql.len()));
+        visit(&sql[start..index_at_boundary], kind);
+        index = index_at_boundary;
+    }
+}
+
+/// Opening tag of a dollar-quoted string, e.g. `$$` or `$function$`
+fn dollar_tag(rest: &str) -> Option<&str> {
+    let body = rest.strip_prefix('$')?;
+    let end = body.find('$')?;
+    let tag = &body[..end];
+
+    let valid = tag
+        .chars()
+        .enumerate()
+        .all(|(index, char)| char == '_' || char.is_alphabetic() || (index > 0 && char.is_numeric()));
+
+    valid.then(|| &rest[..end + 2])
+}
+
+/// Splits a script into statements on semicolons outside of comments, quotes, dollar-quoted
+/// bodies and `begin atomic … end` routine bodies, skipping the ones consisting of comments only
+pub fn split_statements(sql: &str) -> Vec<SqlStatement> {
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut position = 0;
+    let mut has_code = false;
+
+    // Nesting of `begin atomic`, `case` and `end` keywords within the current statement
+    let mut atomic_depth = 0;
+    let mut previous_word = String::new();
+
+    let mut push = |start: usize, end: usize, has_code: bool| {
+        if !has_code {
+            return;
+        }
+
+        let text = &sql[start..end];
+        let leading = text.len() - text.trim_start().len();
+
+        statements.push(SqlStatement {
+            text: text.trim().to_string(),
+            offset: start + leading,
+        });
+    };
+
+    scan(sql, |chunk, kind| {
+        if kind == Chunk::Code && chunk == ";" && atomic_depth == 0 {
+            push(start, position, has_code);
+            start = position + 1;
+            has_code = false;
+            previous_word.clear();
+        } else if kind != Chunk::Comment && !chunk.trim().is_empty() {
+            has_code = true;
+        }
+
+        if kind == Chunk::Code {
+            for word in chunk
+                .split(|char: char| !(char.is_alphanumeric() || char == '_'))
+                .filter(|word| !word.is_empty())
+            {
+                let word = word.to_lowercase();
+
+                match word.as_str() {
+                    "atomic" if previous_word == "begin" => atomic_depth += 1,
+                    "case" if atomic_depth > 0 => atomic_depth += 1,
+                    "end" if atomic_depth > 0 => atomic_depth -= 1,
+                    _ => {}
+                }
+
+                previous_word = word;
+            }
+        }
+
+        position += chunk.len();
+    });
+
+    push(start, sql.len(), has_code);
+
+    statements
+}
+
+/// Keywords highlighted by [`highlight`], the ones `supabase db diff` emits along with the
+/// common ones of routine bodies
+static KEYWORDS: &[&str] = &[
+    "add", "alter", "and", "as", "begin", "by", "cascade", "check", "column", "constraint",
+    "create", "declare", "default", "delete", "drop", "else", "end", "enable", "exists",
+    "extension", "for", "foreign", "from", "function", "grant", "if", "in", "index", "insert",
+    "into", "is", "key", "language", "level", "not", "null", "on", "or", "owner", "policy",
+    "primary", "procedure", "publication", "references", "replace", "return", "returns",
+    "revoke", "row", "schema", "security", "select", "sequence", "set", "table", "then", "to",
+    "trigger", "type", "unique", "update", "using", "values", "view", "when", "where", "with",
+];
+
+/// Colors keywords, literals and comments of a script for terminal output, bodies of
+/// dollar-quoted routines are highlighted as code too
+pub fn highlight(sql: &str) -> String {
+    use supercli::output::styling::apply_style;
+
+    let mut result = String::with_capacity(sql.len());
+
+    scan(sql, |chunk, kind| match kind {
+        Chunk::Comment => result.push_str(&apply_style(chunk, "muted").to_string()),
+        Chunk::Quoted => {
+            let tag = dollar_tag(chunk)
+                .filter(|tag| chunk.len() >= tag.len() * 2 && chunk.ends_with(tag));
+
+            match tag {
+                Some(tag) => {
+                    let body = &chunk[tag.len()..chunk.len() - tag.len()];
+                    let tag = apply_style(tag, "dimmed").to_string();
+
+                    result.push_str(&tag);
+                    result.push_str(&highlight(body));
+                    result.push_str(&tag);
+                }
+                None if chunk.starts_with('"') => {
+                    result.push_str(&apply_style(chunk, "property").to_string())
+                }
+                None => result.push_str(&apply_style(chunk, "id").to_string()),
+            }
+        }
+        Chunk::Code => {
+            let mut word = String::new();
+
+            let flush = |word: &mut String, result: &mut String| {
+                if word.is_empty() {
+                    return;
+                }
+
+                if KEYWORDS.contains(&word.to_lowercase().as_str()) {
+                    result.push_str(&apply_style(word, "command").to_string());
+                } else if word.chars().all(|char| char.is_ascii_digit()) {
+                    result.push_str(&apply_style(word, "number").to_string());
+                } else {
+                    result.push_str(word);
+                }
+
+                word.clear();
+            };
+
+            for char in chunk.chars() {
+                if char.is_alphanumeric() || char == '_' {
+                    word.push(char);
+                } else {
+                    flush(&mut word, &mut result);
+                    result.push(char);
+                }
+            }
+
+            flush(&mut word, &mut result);
+        }
+    });
+
+    result
+}
+
+/// Failure of a single statement of a script, pointing at the line and column within the script
+#[derive(Debug)]
+pub struct ScriptError {
+    pub code: String,
+    pub message: String,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    pub where_: Option<String>,
+
+    /// 1-based index of the failed statement
+    pub statement: usize,
+
+    /// Byte offset within the script the error points at
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+
+    /// Text of the script's line the error points at
+    pub excerpt: String,
+
+    /// Whether the statements run before the failed one have been rolled back
+    pub rolled_back: bool,
+}
+
+impl ScriptError {
+    pub fn new(
+        error: &tokio_postgres::error::DbError,
+        script: &str,
+        statement: &SqlStatement,
+        index: usize,
+        rolled_back: bool,
+    ) -> Self {
+        let position = match error.position() {
+            Some(tokio_postgres::error::ErrorPosition::Original(position)) => *position as usize,
+            _ => 1,
+        };
+
+        let mut script_error = Self {
+            code: error.code().code().to_string(),
+            message: error.message().to_string(),
+            detail: error.detail().map(str::to_string),
+            hint: error.hint().map(str::to_string),
+            where_: error.where_().map(|where_| where_.replace('\n', " ")),
+            statement: index + 1,
+            offset: 0,
+            line: 0,
+            column: 0,
+            excerpt: String::new(),
+            rolled_back,
+        };
+
+        script_error.relocate(script, statement.script_offset(script, position));
+        script_error
+    }
+
+    /// Points the error at byte `offset` within `script`, e.g. the source the run script has
+    /// been rendered from
+    pub fn relocate(&mut self, script: &str, offset: usize) {
+        let (line, column) = line_and_column(script, offset);
+
+        self.offset = offset;
+        self.line = line;
+        self.column = column;
+        self.excerpt = script.lines().nth(line - 1).unwrap_or_default().to_string();
+    }
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {}", self.code, self.message)?;
+        writeln!(
+            f,
+            "\tat line {}, column {} (statement #{})",
+            self.line, self.column, self.statement
+        )?;
+
+        let gutter = self.line.to_string();
+
+        writeln!(f, "\t{} | {}", gutter, self.excerpt)?;
+        write!(
+            f,
+            "\t{} | {}^",
+            " ".repeat(gutter.len()),
+            " ".repeat(self.column.saturating_sub(1))
+        )?;
+
+        for (label, value) in [
+            ("detail", &self.detail),
+            ("hint", &self.hint),
+            ("where", &self.where_),
+        ] {
+            if let Some(value) = value {
+                write!(f, "\n\t{label}: {value}")?;
+            }
+        }
+
+        if self.rolled_back {
+            write!(f, "\n\tthe transaction has been rolled back, nothing was applied")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(sql: &str) -> Vec<String> {
+        split_statements(sql)
+            .into_iter()
+            .map(|statement| statement.text)
+            .collect()
+    }
+
+    #[test]
+    fn statements_are_split_on_semicolons_with_their_offsets() {
+        assert_eq!(
+            split_statements("select 1;\n  select 2"),
+            vec![
+                SqlStatement {
+                    text: "select 1".into(),
+                    offset: 0,
+                },
+                SqlStatement {
+                    text: "select 2".into(),
+                    offset: 12,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn semicolons_within_quotes_and_comments_are_kept() {
+        assert_eq!(
+            texts("select ';', \"a;b\";\n-- not; split\nselect 2; /* ; */ select 3;"),
+            vec![
+                "select ';', \"a;b\"",
+                "-- not; split\nselect 2",
+                "/* ; */ select 3"
+            ]
+        );
+    }
+
+    #[test]
+    fn statements_of_comments_only_are_skipped() {
+        assert_eq!(
+            texts("select 1;\n-- trailing; comment\n/* ; */"),
+            vec!["select 1"]
+        );
+    }
+
+    #[test]
+    fn dollar_quoted_bodies_are_kept_whole() {
+        assert_eq!(
+            texts(
+                "create function f() returns int language plpgsql as $body$ begin return 1; end $body$;\nselect $$;$$;"
+            ),
+            vec![
+                "create function f() returns int language plpgsql as $body$ begin return 1; end $body$",
+                "select $$;$$",
+            ]
+        );
+    }
+
+    #[test]
+    fn begin_atomic_bodies_are_kept_whole() {
+        assert_eq!(
+            texts(
+                "create function f() returns int language sql\nbegin atomic\n  select case when true then 1 end;\n  select 2;\nend;\nselect 3;"
+            ),
+            vec![
+                "create function f() returns int language sql\nbegin atomic\n  select case when true then 1 end;\n  select 2;\nend",
+                "select 3",
+            ]
+        );
+    }
+}
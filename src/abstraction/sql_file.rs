@@ -0,0 +1,323 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use regex::Regex;
+
+use crate::{
+    abstraction::{SqlStatement, split_statements},
+    errors::NoWay,
+};
+
+pub static MANIFEST_FILENAME: &str = "sbp.order";
+
+lazy_static::lazy_static! {
+    static ref DEFINITION: Regex = Regex::new(
+        r#"(?is)^\s*create\s+(?:or\s+replace\s+)?(?:(?:temp|temporary|unlogged)\s+)?(?:recursive\s+)?(?:materialized\s+)?(?:function|procedure|view|type|table|domain|sequence)\s+(?:if\s+not\s+exists\s+)?(?:(?:"(?:[^"]|"")+"|[\w$]+)\s*\.\s*)?("(?:[^"]|"")+"|[\w$]+)"#
+    )
+    .no_way_because("the pattern is a valid regex");
+
+    static ref DEPENDS: Regex = Regex::new(r"(?im)^\s*--\s*depends\s*:(.*)$")
+        .no_way_because("the pattern is a valid regex");
+}
+
+/// Watched SQL file along with what it defines and references, used to run files in an order
+/// satisfying their dependencies
+pub struct SqlFile {
+    pub path: PathBuf,
+    pub sql: String,
+    statements: Vec<SqlStatement>,
+}
+
+impl SqlFile {
+    pub fn read(path: PathBuf) -> anyhow::Result<Self> {
+        let sql = std::fs::read_to_string(&path).with_context(|| {
+            styled_error!(
+                "Make sure the file at {} is a valid UTF-8 file",
+                (&path.to_string_lossy(), "file_path")
+            )
+        })?;
+
+        Ok(Self::new(path, sql))
+    }
+
+    pub fn new(path: PathBuf, sql: String) -> Self {
+        let statements = split_statements(&sql);
+
+        Self {
+            path,
+            sql,
+            statements,
+        }
+    }
+
+    /// Names of functions, procedures, views, types, tables, domains and sequences the file
+    /// creates, lowercased unless quoted
+    fn definitions(&self) -> HashSet<String> {
+        self.statements
+            .iter()
+            .filter_map(|statement| {
+                let code = statement.code();
+                let name = DEFINITION.captures(&code)?.get(1)?.as_str().to_string();
+
+                Some(match name.strip_prefix('"') {
+                    Some(quoted) => quoted.trim_end_matches('"').replace("\"\"", "\""),
+                    None => name.to_lowercase(),
+                })
+            })
+            .collect()
+    }
+
+    /// Every word of the file outside of comments, candidates for references to other files'
+    /// definitions
+    fn words(&self) -> HashSet<String> {
+        self.statements
+            .iter()
+            .flat_map(|statement| {
+                statement
+                    .code()
+                    .split(|char: char| !(char.is_alphanumeric() || char == '_' || char == '$'))
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_lowercase)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Files listed in `-- depends: a.sql, b.sql` headers, relative to the file's directory
+    fn declared_dependencies(&self) -> Vec<PathBuf> {
+        let directory = self.path.parent().unwrap_or(Path::new(""));
+
+        DEPENDS
+            .captures_iter(&self.sql)
+            .flat_map(|captures| {
+                captures[1]
+                    .split([',', ' '])
+                    .filter(|path| !path.is_empty())
+                    .map(|path| normalize(&directory.join(path)))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Paths listed in the manifest file of the directory, one per line relative to it, `#`
+    /// starting a comment
+    pub fn manifest(directory: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let path = directory.join(MANIFEST_FILENAME);
+
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let manifest = std::fs::read_to_string(&path).with_context(|| {
+            styled_error!("Couldn't read {}", (&path.to_string_lossy(), "file_path"))
+        })?;
+
+        Ok(manifest
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| normalize(&directory.join(line)))
+            .collect())
+    }
+
+    /// Orders files so that each comes after the ones it depends on, keeping the original
+    /// order otherwise.
+    ///
+    /// Dependencies are detected from names the files define and reference, `-- depends:`
+    /// headers and the `manifest` (each listed file depending on the previous one) override
+    /// detected dependencies pointing the other way, files in a cycle keep their original order
+    pub fn order(files: Vec<SqlFile>, manifest: &[PathBuf]) -> Vec<SqlFile> {
+        let paths = files
+            .iter()
+            .map(|file| normalize(&file.path))
+            .collect::<Vec<_>>();
+
+        let index_of = |path: &PathBuf| paths.iter().position(|candidate| candidate == path);
+
+        let mut explicit = BTreeSet::<(usize, usize)>::new();
+
+        for (index, file) in files.iter().enumerate() {
+            for dependency in file.declared_dependencies() {
+                if let Some(dependency) = index_of(&dependency).filter(|other| *other != index) {
+                    explicit.insert((index, dependency));
+                }
+            }
+        }
+
+        let listed = manifest.iter().filter_map(index_of).collect::<Vec<_>>();
+
+        for pair in listed.windows(2) {
+            explicit.insert((pair[1], pair[0]));
+        }
+
+        let definitions = files.iter().map(SqlFile::definitions).collect::<Vec<_>>();
+        let mut edges = explicit.clone();
+
+        for (index, file) in files.iter().enumerate() {
+            let words = file.words();
+
+            for (other, defined) in definitions.iter().enumerate() {
+                let overridden = explicit.contains(&(other, index))
+                    || (listed.contains(&index) && listed.contains(&other));
+
+                if other != index && !overridden && !defined.is_disjoint(&words) {
+                    edges.insert((index, other));
+                }
+            }
+        }
+
+        // Kahn's algorithm, always picking the earliest ready file to stay close to the
+        // original order
+        let mut remaining = (0..files.len()).collect::<BTreeSet<_>>();
+        let mut order = Vec::with_capacity(files.len());
+
+        while !remaining.is_empty() {
+            let ready = remaining
+                .iter()
+                .copied()
+                .find(|index| {
+                    !edges
+                        .iter()
+                        .any(|(file, dependency)| file == index && remaining.contains(dependency))
+                })
+                .unwrap_or_else(|| *remaining.first().unwrap_or(&0));
+
+            remaining.remove(&ready);
+            order.push(ready);
+        }
+
+        let mut files = files.into_iter().map(Some).collect::<Vec<_>>();
+
+        order
+            .into_iter()
+            .filter_map(|index| files[index].take())
+            .collect()
+    }
+}
+
+/// Lexically resolves `.` and `..` components so paths from headers, the manifest and the
+/// glob can be compared
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, sql: &str) -> SqlFile {
+        SqlFile::new(PathBuf::from(path), sql.to_string())
+    }
+
+    fn order(files: Vec<SqlFile>, manifest: &[&str]) -> Vec<String> {
+        let manifest = manifest.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        SqlFile::order(files, &manifest)
+            .into_iter()
+            .map(|file| file.path.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn files_come_after_definitions_they_reference() {
+        assert_eq!(
+            order(
+                vec![
+                    file(
+                        "sql/view.sql",
+                        "create view active_items as select * from items;"
+                    ),
+                    file("sql/other.sql", "select 1;"),
+                    file(
+                        "sql/table.sql",
+                        "create table if not exists public.items (id int);"
+                    ),
+                ],
+                &[]
+            ),
+            vec!["sql/other.sql", "sql/table.sql", "sql/view.sql"]
+        );
+    }
+
+    #[test]
+    fn references_within_comments_are_ignored() {
+        assert_eq!(
+            order(
+                vec![
+                    file("sql/a.sql", "-- uses items\nselect 1;"),
+                    file("sql/b.sql", "create table items (id int);"),
+                ],
+                &[]
+            ),
+            vec!["sql/a.sql", "sql/b.sql"]
+        );
+    }
+
+    #[test]
+    fn depends_headers_override_detected_dependencies() {
+        let files = |header: &str| {
+            vec![
+                file(
+                    "sql/x.sql",
+                    "create function f() returns int language sql as 'select g()';",
+                ),
+                file(
+                    "sql/y.sql",
+                    &format!("{header}create function g() returns int language sql as 'select 1';"),
+                ),
+            ]
+        };
+
+        assert_eq!(order(files(""), &[]), vec!["sql/y.sql", "sql/x.sql"]);
+
+        assert_eq!(
+            order(files("-- depends: ./x.sql\n"), &[]),
+            vec!["sql/x.sql", "sql/y.sql"]
+        );
+    }
+
+    #[test]
+    fn manifest_order_is_followed() {
+        assert_eq!(
+            order(
+                vec![
+                    file("sql/a.sql", "create view a as select * from b;"),
+                    file("sql/b.sql", "create view b as select 1;"),
+                    file("sql/c.sql", "select 1;"),
+                ],
+                &["sql/c.sql", "sql/a.sql", "sql/b.sql"]
+            ),
+            vec!["sql/c.sql", "sql/a.sql", "sql/b.sql"]
+        );
+    }
+
+    #[test]
+    fn files_in_a_cycle_keep_their_order() {
+        assert_eq!(
+            order(
+                vec![
+                    file("sql/a.sql", "create view a as select * from b;"),
+                    file("sql/b.sql", "create view b as select * from a;"),
+                    file("sql/c.sql", "create view c as select * from a;"),
+                ],
+                &[]
+            ),
+            vec!["sql/a.sql", "sql/b.sql", "sql/c.sql"]
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use anyhow::Context;
+use tokio::process::Command;
+
+use crate::config::{CONFIG, CliSource};
+use crate::sys::find_executable;
+
+lazy_static::lazy_static! {
+    static ref LOCATED: anyhow::Result<SupabaseCli> = SupabaseCli::locate();
+}
+
+/// Resolved way of invoking the `supabase` CLI
+#[derive(Debug, Clone, PartialEq, Eq, strum_macros::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum SupabaseCli {
+    /// Raw command set in `supabase.command`
+    Command(String),
+
+    /// Binary pointed by `supabase.path`
+    Path(PathBuf),
+
+    /// `supabase` binary found on `PATH`
+    Global(PathBuf),
+
+    Bunx(String),
+    Pnpm(String),
+    Npx(String),
+}
+
+impl SupabaseCli {
+    /// The CLI resolved once per process from the configuration
+    pub fn current() -> anyhow::Result<&'static Self> {
+        LOCATED
+            .as_ref()
+            .map_err(|error| anyhow::anyhow!(error.to_string()))
+    }
+
+    fn locate() -> anyhow::Result<Self> {
+        let config = &CONFIG.supabase;
+
+        if let Some(command) = &config.command {
+            return Ok(Self::Command(command.clone()));
+        }
+
+        let version = config.version.clone();
+
+        Ok(match config.source {
+            CliSource::Auto => Self::detect()?,
+            CliSource::Path => {
+                let Some(path) = config.path.clone() else {
+                    crate::styled_bail!(
+                        "The `{}` source requires `{}` to be set",
+                        ("path", "id"),
+                        ("supabase.path", "property")
+                    );
+                };
+
+                if !path.is_file() {
+                    crate::styled_bail!(
+                        "There's no Supabase CLI binary at `{}`",
+                        (path.display().to_string(), "file_path")
+                    );
+                }
+
+                Self::Path(path)
+            }
+            CliSource::Global => Self::Global(find_executable("supabase").with_context(|| {
+                styled_error!(
+                    "Couldn't find `{}` on your `{}`",
+                    ("supabase", "command"),
+                    ("PATH", "property")
+                )
+            })?),
+            CliSource::Bunx => Self::Bunx(version),
+            CliSource::Pnpm => Self::Pnpm(version),
+            CliSource::Npx => Self::Npx(version),
+        })
+    }
+
+    fn detect() -> anyhow::Result<Self> {
+        let config = &CONFIG.supabase;
+        let version = config.version.clone();
+
+        if let Some(path) = config.path.as_ref().filter(|path| path.is_file()) {
+            return Ok(Self::Path(path.clone()));
+        }
+
+        if version == "latest" {
+            if let Some(path) = find_executable("supabase") {
+                return Ok(Self::Global(path));
+            }
+        }
+
+        if find_executable("bunx").is_some() {
+            return Ok(Self::Bunx(version));
+        }
+
+        if find_executable("pnpm").is_some() {
+            return Ok(Self::Pnpm(version));
+        }
+
+        if find_executable("npx").is_some() {
+            return Ok(Self::Npx(version));
+        }
+
+        crate::styled_bail!(
+            "Couldn't find a way to run the Supabase CLI, install `{}` globally or make one of `{}`, `{}`, `{}` available",
+            ("supabase", "command"),
+            ("bunx", "command"),
+            ("pnpm", "command"),
+            ("npx", "command")
+        );
+    }
+
+    /// Shell snippet the CLI arguments get appended to
+    pub fn invocation(&self) -> String {
+        match self {
+            Self::Command(command) => command.clone(),
+            Self::Path(path) | Self::Global(path) => format!("'{}'", path.display()),
+            Self::Bunx(version) => format!("bunx supabase@{version}"),
+            Self::Pnpm(version) => format!("pnpm --silent dlx supabase@{version}"),
+            Self::Npx(version) => format!("npx --yes --loglevel=error supabase@{version}"),
+        }
+    }
+
+    pub async fn version(&self) -> anyhow::Result<String> {
+        let output = Command::new("sh")
+            .stdin(Stdio::null())
+            .arg("-c")
+            .arg(format!("{} --version", self.invocation()))
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            anyhow::bail!(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
@@ -7,8 +7,17 @@ pub enum MigrationStatus {
     Reverted,
 }
 
+pub mod cli;
+pub use cli::*;
+
+pub mod config;
+pub use config::*;
+
 pub mod project;
 pub use project::*;
 
 pub mod runtime;
 pub use runtime::*;
+
+pub mod shadow;
+pub use shadow::*;
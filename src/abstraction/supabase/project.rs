@@ -1,8 +1,12 @@
-use crate::abstraction::{Migration, MigrationStatus, NO_DOCKER, SupabaseRuntime, containers};
+use crate::abstraction::{
+    Catalog, Migration, MigrationStatus, NO_DOCKER, PublishedTable, ReplicaIdentity, RoutineKind,
+    ShadowDb, StorageBucket, SupabaseConfig, SupabaseRuntime, TableIdentity, TableRef,
+    containers, split_statements,
+};
 use crate::errors::NoWay;
 
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{fs::File, io::Write};
 
 use anyhow::Context;
@@ -11,9 +15,29 @@ use bollard::{Docker, models::ContainerSummary};
 use chrono::Utc;
 use regex::Regex;
 
+/// Schemas that come with Postgres or Supabase services, never committed by the user
+static MANAGED_SCHEMAS: &[&str] = &[
+    "information_schema",
+    "auth",
+    "storage",
+    "realtime",
+    "extensions",
+    "graphql",
+    "graphql_public",
+    "net",
+    "pgbouncer",
+    "pgsodium",
+    "pgsodium_masks",
+    "supabase_functions",
+    "supabase_migrations",
+    "vault",
+    "cron",
+    "pgtle",
+];
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct SupabaseProject {
-    pub(crate) project_id: String,
+    pub(crate) config: SupabaseConfig,
     pub(crate) root: Option<PathBuf>,
 }
 
@@ -31,6 +55,15 @@ pub enum DbDiffError {
 
 impl SupabaseProject {
     pub async fn from_cwd() -> anyhow::Result<Self> {
+        let result = Self::find()?;
+
+        result.runtime().validate().await?;
+
+        Ok(result)
+    }
+
+    /// Same as [`from_cwd`](Self::from_cwd) but doesn't check if the project is the one running
+    pub fn find() -> anyhow::Result<Self> {
         let root = std::env::current_dir().context(
             "Failed to get current directory to indicate the project, make sure the current directory exists (sic!) and you have the necessary permissions"
         )?;
@@ -42,49 +75,12 @@ impl SupabaseProject {
             )
         })?;
 
-        let config_path = root.join("supabase/config.toml");
-
-        let config = std::fs::read_to_string(config_path)
-            .no_way_because("The config file has been found earlier");
-
-        let project_id = config
-            .lines()
-            .find(|line| line.starts_with("project_id"))
-            .with_context(|| styled_error!(
-                "Failed to find `{}` in `{}`, make sure the `{}` has no syntax errors and contains a `{}`",
-                ("project_id", "property"),
-                ("config.toml", "file_path"),
-                ("config.toml", "file_path"),
-                ("project_id", "property")
-            ))?
-            .splitn(2, '=')
-            .nth(1)
-            .with_context(|| styled_error!(
-                "Failed to parse `{}` in `{}`, make sure the `{}` kv pair has no syntax errors",
-                ("project_id", "property"),
-                ("config.toml", "file_path"),
-                ("project_id", "property")
-            ))?
-            .trim()
-            .to_string();
-
-        let project_id: String = serde_json::from_str(&project_id).with_context(|| {
-            styled_error!(
-                "Failed to parse `{}` in `{}`, make sure the `{}` value has no syntax errors",
-                ("project_id", "property"),
-                ("config.toml", "file_path"),
-                ("project_id", "property")
-            )
-        })?;
+        let config = SupabaseConfig::load(&root.join("supabase/config.toml"))?;
 
-        let result = Self {
-            project_id,
+        Ok(Self {
+            config,
             root: Some(root),
-        };
-
-        result.runtime().validate().await?;
-
-        Ok(result)
+        })
     }
 
     fn find_root(path: PathBuf) -> Option<PathBuf> {
@@ -124,7 +120,6 @@ impl SupabaseProject {
         let name = migration.migration_name();
         let sql = migration.sql();
 
-        let timecode = Utc::now().format("%Y%m%d%H%M%S").to_string();
         let migrations_dir = self.migrations_dir();
 
         if !migrations_dir.exists() {
@@ -141,6 +136,7 @@ impl SupabaseProject {
             );
         }
 
+        let timecode = Self::free_timecode(&migrations_dir);
         let file_path = migrations_dir.join(format!("{timecode}_{name}.sql"));
 
         let mut file = File::create(file_path).with_context(|| {
@@ -171,6 +167,31 @@ impl SupabaseProject {
         Ok(())
     }
 
+    /// Current timecode, moved forward if a migration with it already exists, e.g. when a few
+    /// migrations are created within the same second
+    fn free_timecode(migrations_dir: &Path) -> String {
+        let mut time = Utc::now();
+
+        loop {
+            let timecode = time.format("%Y%m%d%H%M%S").to_string();
+            let prefix = format!("{timecode}_");
+
+            let taken = std::fs::read_dir(migrations_dir)
+                .map(|entries| {
+                    entries.filter_map(Result::ok).any(|entry| {
+                        entry.file_name().to_string_lossy().starts_with(&prefix)
+                    })
+                })
+                .unwrap_or_default();
+
+            if !taken {
+                return timecode;
+            }
+
+            time += chrono::Duration::seconds(1);
+        }
+    }
+
     pub async fn mark_timecode(
         &self,
         timecode: &str,
@@ -189,10 +210,124 @@ impl SupabaseProject {
         Ok(())
     }
 
-    pub async fn db_diff(&self, schema: &str) -> anyhow::Result<Option<String>, DbDiffError> {
+    /// Diffs the schemas in a single `db diff` run, so the shadow database boots once and
+    /// statements stay in the order they depend on each other in
+    pub async fn db_diff(&self, schemas: &[String]) -> anyhow::Result<Option<String>, DbDiffError> {
+        let sql = self.run_db_diff(&schemas.join(",")).await?;
+
+        Ok(sql.map(|sql| {
+            if schemas.len() > 1 {
+                Self::label_schemas(&sql, schemas)
+            } else {
+                sql
+            }
+        }))
+    }
+
+    /// Precedes each run of consecutive statements of the same schema with a header, without
+    /// reordering anything, statements not naming any of the schemas stay in the current run
+    fn label_schemas(sql: &str, schemas: &[String]) -> String {
+        let mut labeled = String::with_capacity(sql.len());
+        let mut current = None;
+        let mut copied = 0;
+
+        for statement in split_statements(sql) {
+            let schema = statement
+                .qualifiers()
+                .into_iter()
+                .find(|qualifier| schemas.contains(qualifier));
+
+            let Some(schema) = schema.filter(|schema| current.as_ref() != Some(schema)) else {
+                continue;
+            };
+
+            labeled.push_str(sql[copied..statement.offset].trim_end());
+
+            if !labeled.is_empty() {
+                labeled.push_str("\n\n");
+            }
+
+            labeled.push_str(&format!("-- Schema: {}\n\n", schema));
+            copied = statement.offset;
+            current = Some(schema);
+        }
+
+        labeled.push_str(&sql[copied..]);
+        labeled
+    }
+
+    /// Same as [`db_diff`](Self::db_diff) but compares catalogs of the local database with the
+    /// ones of a warm shadow database directly, without the `supabase` CLI
+    pub async fn native_db_diff(&self, schemas: &[String]) -> anyhow::Result<Option<String>> {
+        let shadow = ShadowDb::warm(self).await?;
+        let mut sections = Vec::new();
+        let mut foreign_schemas = Vec::new();
+
+        for schema in schemas {
+            let before = Catalog::load(&shadow.project, schema).await?;
+            let after = Catalog::load(self, schema).await?;
+            let statements = before.diff(&after)?;
+
+            if !statements.is_empty() {
+                foreign_schemas.push((
+                    schema,
+                    before
+                        .foreign_schemas()
+                        .union(after.foreign_schemas())
+                        .cloned()
+                        .collect::<Vec<_>>(),
+                ));
+            }
+
+            sections.push((
+                schema,
+                (!statements.is_empty()).then(|| statements.join("\n\n") + "\n"),
+            ));
+        }
+
+        // Sections go one schema after another, which can't follow objects referring to each
+        // other across them
+        for (schema, referenced) in &foreign_schemas {
+            if let Some(other) = referenced
+                .iter()
+                .find(|other| foreign_schemas.iter().any(|(changed, _)| changed == other))
+            {
+                crate::styled_bail!(
+                    "Changes of `{}` referring to changed `{}` aren't supported by the native diff",
+                    (schema, "id"),
+                    (other, "id")
+                );
+            }
+        }
+
+        Ok(Self::join_sections(sections))
+    }
+
+    /// Joins diffs of schemas, preceding each with a header when there's more than one schema
+    fn join_sections(sections: Vec<(&String, Option<String>)>) -> Option<String> {
+        if sections.len() == 1 {
+            return sections.into_iter().next().and_then(|(_, sql)| sql);
+        }
+
+        let sections = sections
+            .into_iter()
+            .filter_map(|(schema, sql)| sql.map(|sql| (schema, sql)))
+            .collect::<Vec<_>>();
+
+        (!sections.is_empty()).then(|| {
+            sections
+                .into_iter()
+                .map(|(schema, sql)| format!("-- Schema: {}\n\n{}", schema, sql.trim_end()))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+                + "\n"
+        })
+    }
+
+    async fn run_db_diff(&self, schemas: &str) -> anyhow::Result<Option<String>, DbDiffError> {
         self.kill_shadow_db().await?;
 
-        let command = format!("db diff --schema {}", schema);
+        let command = format!("db diff --schema {}", schemas);
 
         tokio::select! {
             output = self.runtime().command_silent(&command) => {
@@ -203,7 +338,7 @@ impl SupabaseProject {
                 if output.status.success() {
                     let value = String::from_utf8_lossy(&output.stdout);
 
-                    return Ok(if value.is_empty() {
+                    return Ok(if value.trim().is_empty() {
                         None
                     } else {
                         Some(value.to_string())
@@ -222,7 +357,7 @@ impl SupabaseProject {
     }
 
     pub async fn kill_shadow_db(&self) -> anyhow::Result<()> {
-        let maybe_shadow_db = containers::shadow_db()
+        let maybe_shadow_db = containers::shadow_db(self.config.db.shadow_port)
             .await?
             .and_then(|container| container.id);
 
@@ -265,7 +400,7 @@ impl SupabaseProject {
     }
 
     pub fn id(&self) -> &str {
-        &self.project_id
+        &self.config.project_id
     }
 
     pub async fn migrations_table(&self, linked: bool) -> anyhow::Result<Vec<(String, bool)>> {
@@ -355,33 +490,245 @@ impl SupabaseProject {
         Ok(results)
     }
 
-    pub async fn tables(&self, schema: &str) -> anyhow::Result<Vec<String>> {
+    pub async fn tables(&self, schemas: &[String]) -> anyhow::Result<Vec<TableRef>> {
         let result = self
             .runtime()
             .query(
-                "select tablename from pg_tables where schemaname = $1",
-                &[&schema],
+                "select schemaname::text, tablename::text from pg_tables where schemaname::text = any($1::text[]) order by 1, 2",
+                &[&schemas],
             )
             .await
             .with_context(|| {
-                styled_error!("Couldn't fetch tables for `{}` schema", (schema, "id"))
+                styled_error!("Couldn't fetch tables for `{}` schemas", (schemas.join(", "), "id"))
             })?;
 
+        Ok(result
+            .into_iter()
+            .map(|row| TableRef {
+                schema: row.get(0),
+                name: row.get(1),
+            })
+            .collect())
+    }
+
+    /// Schemas created by the user, i.e. all but the system ones and those managed by Supabase
+    pub async fn user_schemas(&self) -> anyhow::Result<Vec<String>> {
+        let result = self
+            .runtime()
+            .query(
+                "select nspname::text from pg_namespace
+                where nspname::text <> all($1::text[])
+                    and nspname not like 'pg\\_%'
+                    and nspname not like '\\_%'
+                order by 1",
+                &[&MANAGED_SCHEMAS],
+            )
+            .await
+            .context("Couldn't fetch schemas")?;
+
         Ok(result.into_iter().map(|row| row.get(0)).collect())
     }
 
-    pub async fn realtime_tables(&self, schema: &str) -> anyhow::Result<Vec<String>> {
+    pub async fn publication_exists(&self, publication: &str) -> anyhow::Result<bool> {
+        let result = self
+            .runtime()
+            .query(
+                "select 1 from pg_publication where pubname = $1",
+                &[&publication],
+            )
+            .await
+            .with_context(|| styled_error!("Couldn't fetch `{}` publication", (publication, "id")))?;
+
+        Ok(!result.is_empty())
+    }
+
+    pub async fn publication_tables(
+        &self,
+        publication: &str,
+        schemas: &[String],
+    ) -> anyhow::Result<Vec<PublishedTable>> {
         let result = self
             .runtime()
             .query(
-                "select tablename from pg_publication_tables where schemaname = $1 and pubname = 'supabase_realtime'",
-                &[&schema]
+                "select n.nspname::text, c.relname::text,
+                    case when pr.prattrs is null then null else (
+                        select array_agg(a.attname::text order by a.attnum)
+                        from pg_attribute a
+                        where a.attrelid = c.oid and a.attnum = any(pr.prattrs::int2[])
+                    ) end,
+                    pg_get_expr(pr.prqual, pr.prrelid)
+                from pg_publication p
+                join pg_publication_rel pr on pr.prpubid = p.oid
+                join pg_class c on c.oid = pr.prrelid
+                join pg_namespace n on n.oid = c.relnamespace
+                where p.pubname = $1 and n.nspname::text = any($2::text[])
+                order by 1, 2",
+                &[&publication, &schemas],
             )
             .await
             .with_context(|| {
-                styled_error!("Couldn't fetch tables for `{}` schema", (schema, "id"))
+                styled_error!(
+                    "Couldn't fetch tables of `{}` publication for `{}` schemas",
+                    (publication, "id"),
+                    (schemas.join(", "), "id")
+                )
             })?;
 
+        Ok(result
+            .into_iter()
+            .map(|row| PublishedTable {
+                table: TableRef {
+                    schema: row.get(0),
+                    name: row.get(1),
+                },
+                columns: row.get(2),
+                row_filter: row.get(3),
+            })
+            .collect())
+    }
+
+    pub async fn replica_identities(
+        &self,
+        schemas: &[String],
+    ) -> anyhow::Result<Vec<TableIdentity>> {
+        let result = self
+            .runtime()
+            .query(
+                "select n.nspname::text, c.relname::text, c.relreplident::text,
+                    (
+                        select ic.relname::text
+                        from pg_index i
+                        join pg_class ic on ic.oid = i.indexrelid
+                        where i.indrelid = c.oid and i.indisreplident
+                    ),
+                    coalesce((
+                        select array_agg(ic.relname::text order by ic.relname)
+                        from pg_index i
+                        join pg_class ic on ic.oid = i.indexrelid
+                        where i.indrelid = c.oid and i.indisunique and i.indimmediate
+                            and not i.indisprimary and i.indpred is null and i.indexprs is null
+                    ), '{}'::text[])
+                from pg_class c
+                join pg_namespace n on n.oid = c.relnamespace
+                where c.relkind in ('r', 'p') and n.nspname::text = any($1::text[])
+                order by 1, 2",
+                &[&schemas],
+            )
+            .await
+            .with_context(|| {
+                styled_error!(
+                    "Couldn't fetch replica identities for `{}` schemas",
+                    (schemas.join(", "), "id")
+                )
+            })?;
+
+        Ok(result
+            .into_iter()
+            .map(|row| TableIdentity {
+                table: TableRef {
+                    schema: row.get(0),
+                    name: row.get(1),
+                },
+                identity: ReplicaIdentity::from_catalog(row.get(2), row.get(3)),
+                indexes: row.get(4),
+            })
+            .collect())
+    }
+
+    /// Schema-qualified signatures of every overload of the routine, usable in `drop` statements,
+    /// unqualified names are looked up in the current schema
+    pub async fn routine_signatures(
+        &self,
+        kind: RoutineKind,
+        schema: Option<&str>,
+        name: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let result = self
+            .runtime()
+            .query(
+                "select format('%I.%I(%s)', n.nspname, p.proname, pg_get_function_identity_arguments(p.oid))
+                from pg_proc p
+                join pg_namespace n on n.oid = p.pronamespace
+                where p.proname = $1
+                    and n.nspname = coalesce($2, current_schema())
+                    and p.prokind::text = $3",
+                &[&name, &schema, &kind.prokind()],
+            )
+            .await
+            .with_context(|| styled_error!("Couldn't look up overloads of `{}`", (name, "id")))?;
+
+        Ok(result.into_iter().map(|row| row.get(0)).collect())
+    }
+
+    pub async fn buckets(&self) -> anyhow::Result<Vec<StorageBucket>> {
+        let result = self
+            .runtime()
+            .query(
+                "select id, coalesce(public, false), coalesce(allowed_mime_types, '{}'), file_size_limit, coalesce(avif_autodetection, false) from storage.buckets order by id",
+                &[],
+            )
+            .await
+            .with_context(|| styled_error!("Couldn't fetch buckets from `{}`", ("storage.buckets", "id")))?;
+
+        Ok(result
+            .into_iter()
+            .map(|row| StorageBucket {
+                id: row.get(0),
+                public: row.get(1),
+                mime_types: row.get(2),
+                file_size_limit: row
+                    .get::<_, Option<i64>>(3)
+                    .map(|limit| limit.max(0) as u64),
+                avif_autodetection: row.get(4),
+            })
+            .collect())
+    }
+
+    /// Columns of a `"storage"` table along with their types, skipping ones of types defined
+    /// outside `pg_catalog`, none when there's no such table
+    pub async fn storage_columns(&self, table: &str) -> anyhow::Result<Vec<(String, String)>> {
+        let name = format!("storage.{table}");
+
+        let result = self
+            .runtime()
+            .query(
+                "select a.attname::text, format_type(a.atttypid, a.atttypmod)
+                from pg_attribute a
+                join pg_type t on t.oid = a.atttypid
+                where a.attrelid = to_regclass($1)
+                    and a.attnum > 0
+                    and not a.attisdropped
+                    and t.typnamespace = 'pg_catalog'::regnamespace
+                order by a.attnum",
+                &[&name],
+            )
+            .await
+            .with_context(|| styled_error!("Couldn't fetch columns of `{}`", (&name, "id")))?;
+
+        Ok(result
+            .into_iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect())
+    }
+
+    /// `create or replace` statements of functions in the `"storage"` schema, e.g. the
+    /// `storage.foldername` helper storage policies use
+    pub async fn storage_functions(&self) -> anyhow::Result<Vec<String>> {
+        let result = self
+            .runtime()
+            .query(
+                "select pg_get_functiondef(p.oid)
+                from pg_proc p
+                join pg_namespace n on n.oid = p.pronamespace
+                where n.nspname = 'storage'
+                    and p.prokind = 'f'
+                    and not exists (select 1 from pg_depend d where d.objid = p.oid and d.deptype = 'e')
+                order by p.proname",
+                &[],
+            )
+            .await
+            .with_context(|| styled_error!("Couldn't fetch functions of `{}`", ("storage", "id")))?;
+
         Ok(result.into_iter().map(|row| row.get(0)).collect())
     }
 }
@@ -399,7 +746,10 @@ impl TryInto<SupabaseProject> for ContainerSummary {
             }
 
             return Ok(SupabaseProject {
-                project_id: slug.to_string(),
+                config: SupabaseConfig {
+                    project_id: slug.to_string(),
+                    ..Default::default()
+                },
                 root: None,
             });
         }
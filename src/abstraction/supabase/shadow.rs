@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use bollard::{
+    Docker,
+    models::{ContainerCreateBody, HostConfig, PortBinding},
+    query_parameters::{
+        CreateContainerOptionsBuilder, RemoveContainerOptionsBuilder, StartContainerOptions,
+    },
+};
+use tokio_postgres::NoTls;
+
+use crate::abstraction::{
+    NO_DOCKER, SupabaseProject, checksum, containers, quote_ident, quote_literal,
+};
+use crate::config::CONFIG;
+
+/// How long the shadow database gets to accept connections after its container starts
+static STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Throwaway database the project's migrations are replayed on, so what they produce can be
+/// told apart from the local state, e.g. for entities `db diff` ignores
+pub struct ShadowDb {
+    /// Copy of the project pointed at the shadow database, for querying it
+    pub project: SupabaseProject,
+}
+
+impl ShadowDb {
+    /// Starts a container from the image of the local database on the shadow port, it's removed
+    /// once [`stop`](Self::stop)ped
+    pub async fn start(project: &SupabaseProject) -> anyhow::Result<Self> {
+        let image = Self::image(project).await?;
+
+        project.kill_shadow_db().await?;
+
+        let docker = Docker::connect_with_socket_defaults().with_context(|| NO_DOCKER.clone())?;
+        let name = format!("sbp_shadow_{}", project.id());
+        let port = project.config.db.shadow_port;
+
+        // One left over by a crashed run may still be there, as auto removal isn't immediate
+        Self::remove(&docker, &name).await;
+        Self::create(&docker, &image, &name, port, true).await?;
+
+        let shadow = Self::at(project, port);
+        shadow.wait().await?;
+
+        Ok(shadow)
+    }
+
+    /// Shadow database kept running on `diff.shadow_port` across runs, only migrations added
+    /// since the last run are applied to it, tracked by their checksums, it's recreated when
+    /// an already applied one has changed, one fails to apply or the image of the local database
+    /// is a different one
+    pub async fn warm(project: &SupabaseProject) -> anyhow::Result<Self> {
+        let image = Self::image(project).await?;
+
+        let docker = Docker::connect_with_socket_defaults().with_context(|| NO_DOCKER.clone())?;
+        let name = format!("sbp_warm_shadow_{}", project.id());
+        let port = CONFIG.diff.shadow_port;
+
+        let reusable = containers::running(&name)
+            .await?
+            .and_then(|container| container.image)
+            .is_some_and(|running| running == image);
+
+        if !reusable {
+            Self::recreate(&docker, &image, &name, port).await?;
+        }
+
+        let shadow = Self::at(project, port);
+        shadow.wait().await?;
+
+        // A failing migration may have been left half applied by an earlier version, so it's
+        // given another go on a fresh database before being reported
+        if !shadow.sync(project).await.unwrap_or(false) {
+            Self::recreate(&docker, &image, &name, port).await?;
+            shadow.wait().await?;
+
+            if !shadow.sync(project).await? {
+                crate::styled_bail!(
+                    "Migrations changed while being applied to the shadow database"
+                );
+            }
+        }
+
+        Ok(shadow)
+    }
+
+    fn at(project: &SupabaseProject, port: u16) -> Self {
+        let mut shadow = project.clone();
+        shadow.config.db.port = port;
+
+        Self { project: shadow }
+    }
+
+    async fn image(project: &SupabaseProject) -> anyhow::Result<String> {
+        let Some(image) = containers::local_db(project.id())
+            .await?
+            .and_then(|container| container.image)
+        else {
+            crate::styled_bail!(
+                "Couldn't find the database container of `{}` to start the shadow database from",
+                (project.id(), "id")
+            );
+        };
+
+        Ok(image)
+    }
+
+    async fn create(
+        docker: &Docker,
+        image: &str,
+        name: &str,
+        port: u16,
+        auto_remove: bool,
+    ) -> anyhow::Result<()> {
+        docker
+            .create_container(
+                Some(CreateContainerOptionsBuilder::default().name(name).build()),
+                ContainerCreateBody {
+                    image: Some(image.to_string()),
+                    env: Some(vec![
+                        "POSTGRES_PASSWORD=postgres".into(),
+                        "POSTGRES_HOST=/var/run/postgresql".into(),
+                        "JWT_SECRET=super-secret-jwt-token-with-at-least-32-characters-long".into(),
+                    ]),
+                    host_config: Some(HostConfig {
+                        port_bindings: Some(HashMap::from([(
+                            "5432/tcp".to_string(),
+                            Some(vec![PortBinding {
+                                host_ip: Some("127.0.0.1".into()),
+                                host_port: Some(port.to_string()),
+                            }]),
+                        )])),
+                        auto_remove: Some(auto_remove),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )
+            .await
+            .with_context(|| {
+                styled_error!("Couldn't create the shadow database `{}`", (name, "id"))
+            })?;
+
+        docker
+            .start_container(name, None::<StartContainerOptions>)
+            .await
+            .with_context(|| {
+                styled_error!("Couldn't start the shadow database `{}`", (name, "id"))
+            })?;
+
+        Ok(())
+    }
+
+    async fn recreate(docker: &Docker, image: &str, name: &str, port: u16) -> anyhow::Result<()> {
+        Self::remove(docker, name).await;
+        Self::create(docker, image, name, port, false).await
+    }
+
+    async fn remove(docker: &Docker, name: &str) {
+        // Fails when there's no such container, which is fine
+        let _ = docker
+            .remove_container(
+                name,
+                Some(RemoveContainerOptionsBuilder::default().force(true).build()),
+            )
+            .await;
+    }
+
+    async fn wait(&self) -> anyhow::Result<()> {
+        let started = Instant::now();
+
+        loop {
+            if tokio_postgres::connect(&self.project.config.db_url(), NoTls)
+                .await
+                .is_ok()
+            {
+                return Ok(());
+            }
+
+            if started.elapsed() > STARTUP_TIMEOUT {
+                crate::styled_bail!(
+                    "The shadow database didn't accept connections within {} seconds",
+                    (&STARTUP_TIMEOUT.as_secs().to_string(), "number")
+                );
+            }
+
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    /// Runs every migration of the project in order
+    pub async fn replay(&self, local: &SupabaseProject) -> anyhow::Result<()> {
+        self.prepare(local).await?;
+
+        for (path, sql) in migrations(local)? {
+            self.apply(&path, &sql).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies migrations that haven't been applied yet, each in a single transaction along with
+    /// its record, `false` when one that has been applied is gone or has changed since
+    async fn sync(&self, local: &SupabaseProject) -> anyhow::Result<bool> {
+        self.prepare(local).await?;
+
+        self.project
+            .runtime()
+            .sql(
+                "create schema if not exists _sbp;
+                create table if not exists _sbp.migrations (name text primary key, checksum text not null);",
+            )
+            .await?;
+
+        let applied = self
+            .project
+            .runtime()
+            .query(
+                "select name, checksum from _sbp.migrations order by name",
+                &[],
+            )
+            .await?
+            .into_iter()
+            .map(|row| (row.get::<_, String>(0), row.get::<_, String>(1)))
+            .collect::<Vec<_>>();
+
+        let migrations = migrations(local)?
+            .into_iter()
+            .map(|(path, sql)| {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                (name, path, sql)
+            })
+            .collect::<Vec<_>>();
+
+        let unchanged = applied.len() <= migrations.len()
+            && applied
+                .iter()
+                .zip(&migrations)
+                .all(|((name, hash), (other, _, sql))| name == other && *hash == checksum(sql));
+
+        if !unchanged {
+            return Ok(false);
+        }
+
+        for (name, path, sql) in &migrations[applied.len()..] {
+            let recorded = format!(
+                "begin;\n{}\n;\ninsert into _sbp.migrations (name, checksum) values ({}, {});\ncommit;",
+                sql,
+                quote_literal(name),
+                quote_literal(&checksum(sql))
+            );
+
+            self.apply(path, &recorded).await?;
+        }
+
+        Ok(true)
+    }
+
+    /// Brings the `"storage"` schema up to date with the local one, as storage migrations don't
+    /// come with the database image: `buckets` and `objects` get the local columns, `objects`
+    /// row level security and the local storage functions are mirrored, so storage policies and
+    /// bucket inserts replay
+    async fn prepare(&self, local: &SupabaseProject) -> anyhow::Result<()> {
+        let mut prelude = vec![
+            "create schema if not exists storage;".to_string(),
+            "create table if not exists storage.buckets (id text primary key, name text not null);"
+                .to_string(),
+            "create table if not exists storage.objects ();".to_string(),
+            "alter table storage.objects enable row level security;".to_string(),
+        ];
+
+        for table in ["buckets", "objects"] {
+            for (column, data_type) in local.storage_columns(table).await? {
+                prelude.push(format!(
+                    "alter table storage.{} add column if not exists {} {};",
+                    table,
+                    quote_ident(&column),
+                    data_type
+                ));
+            }
+        }
+
+        self.project.runtime().sql(&prelude.join("\n")).await?;
+
+        for definition in local.storage_functions().await? {
+            // One relying on something missing from the shadow database is left out, migrations
+            // that don't call it still replay
+            let _ = self
+                .project
+                .runtime()
+                .sql(&format!("set check_function_bodies = off;\n{definition};"))
+                .await;
+        }
+
+        Ok(())
+    }
+
+    async fn apply(&self, path: &Path, sql: &str) -> anyhow::Result<()> {
+        self.project.runtime().sql(sql).await.with_context(|| {
+            styled_error!(
+                "Couldn't replay {} on the shadow database",
+                (&path.display().to_string(), "file_path")
+            )
+        })
+    }
+
+    pub async fn stop(&self) -> anyhow::Result<()> {
+        self.project.kill_shadow_db().await
+    }
+}
+
+/// Migration files of the project along with their content, in the order they're applied in
+fn migrations(project: &SupabaseProject) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    let mut paths = std::fs::read_dir(project.migrations_dir())
+        .context("Failed to read the migrations directory")?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "sql"))
+        .collect::<Vec<_>>();
+
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let sql = std::fs::read_to_string(&path).with_context(|| {
+                styled_error!(
+                    "Make sure the file at {} exists and is a valid UTF-8 file",
+                    (&path.display().to_string(), "file_path")
+                )
+            })?;
+
+            Ok((path, sql))
+        })
+        .collect()
+}
@@ -0,0 +1,211 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::errors::NoWay;
+
+lazy_static::lazy_static! {
+    static ref PLACEHOLDER: Regex =
+        Regex::new(r"\\?\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}")
+            .no_way_because("the pattern is a valid regex");
+}
+
+/// Template with its placeholders replaced, along with where each replacement came from, so
+/// positions within the rendered text can be mapped back to the template
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    pub text: String,
+
+    /// Byte ranges of replaced placeholders within the template and of their values within
+    /// the rendered text, in order
+    substitutions: Vec<(Range<usize>, Range<usize>)>,
+}
+
+impl Rendered {
+    /// Byte offset within the template of the one within the rendered text, the start of the
+    /// placeholder for offsets within its value
+    pub fn template_offset(&self, offset: usize) -> usize {
+        let Some((template, rendered)) = self
+            .substitutions
+            .iter()
+            .take_while(|(_, rendered)| rendered.start <= offset)
+            .last()
+        else {
+            return offset;
+        };
+
+        if offset < rendered.end {
+            template.start
+        } else {
+            template.end + (offset - rendered.end)
+        }
+    }
+}
+
+/// Placeholder without a value, along with the 1-based line it's at
+#[derive(Debug)]
+pub struct MissingVariable {
+    pub name: String,
+    pub line: usize,
+}
+
+/// Replaces `{{ name }}` placeholders with values of `vars`, falling back to environment
+/// variables of the same name, `\{{ name }}` is left as a literal `{{ name }}`
+pub fn render_template(
+    template: &str,
+    vars: &BTreeMap<String, Value>,
+) -> anyhow::Result<Rendered> {
+    render_template_with(template, vars, true).map_err(|missing| {
+        anyhow::anyhow!(styled_error!(
+            "Unknown variable `{}` at line {}, set it in `{}` of `{}` or as an environment variable",
+            (&missing.name, "id"),
+            (&missing.line.to_string(), "number"),
+            ("[vars]", "property"),
+            ("sbp.toml", "file_path")
+        ))
+    })
+}
+
+/// Same as [`render_template`], environment variables are only looked up when `environment`
+/// is on
+pub fn render_template_with(
+    template: &str,
+    vars: &BTreeMap<String, Value>,
+    environment: bool,
+) -> Result<Rendered, MissingVariable> {
+    let mut rendered = Rendered {
+        text: String::with_capacity(template.len()),
+        substitutions: Vec::new(),
+    };
+
+    let mut last = 0;
+
+    for captures in PLACEHOLDER.captures_iter(template) {
+        let Some(placeholder) = captures.get(0) else {
+            continue;
+        };
+
+        let value = match placeholder.as_str().strip_prefix('\\') {
+            Some(literal) => literal.to_string(),
+            None => {
+                let name = &captures[1];
+
+                let value = vars
+                    .get(name)
+                    .or_else(|| vars.get(&name.to_lowercase()))
+                    .map(|value| match value {
+                        Value::String(value) => value.clone(),
+                        other => other.to_string(),
+                    })
+                    .or_else(|| environment.then(|| std::env::var(name).ok()).flatten());
+
+                let Some(value) = value else {
+                    return Err(MissingVariable {
+                        name: name.to_string(),
+                        line: template[..placeholder.start()].matches('\n').count() + 1,
+                    });
+                };
+
+                value
+            }
+        };
+
+        rendered.text.push_str(&template[last..placeholder.start()]);
+
+        let start = rendered.text.len();
+        rendered.text.push_str(&value);
+
+        rendered
+            .substitutions
+            .push((placeholder.range(), start..rendered.text.len()));
+
+        last = placeholder.end();
+    }
+
+    rendered.text.push_str(&template[last..]);
+
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, Value> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), Value::String(value.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn offsets_after_placeholders_are_shifted_back() {
+        let template = "select '{{ name }}', {{ count }} from missing;";
+        let rendered = render_template_with(
+            template,
+            &vars(&[("name", "a much longer value"), ("count", "1")]),
+            false,
+        )
+        .no_way_because("every variable is set");
+
+        assert_eq!(
+            rendered.text,
+            "select 'a much longer value', 1 from missing;"
+        );
+
+        let error = rendered
+            .text
+            .find("missing")
+            .no_way_because("the rendered text has the table name");
+
+        assert_eq!(
+            rendered.template_offset(error),
+            template
+                .find("missing")
+                .no_way_because("the template has the table name")
+        );
+    }
+
+    #[test]
+    fn offsets_before_and_within_placeholders_map_to_the_template() {
+        let template = "selec 1;\nselect {{ value }};";
+        let rendered = render_template_with(template, &vars(&[("value", "'abc'")]), false)
+            .no_way_because("every variable is set");
+
+        assert_eq!(rendered.template_offset(2), 2);
+
+        let value = rendered
+            .text
+            .find("'abc'")
+            .no_way_because("the rendered text has the value");
+
+        assert_eq!(
+            rendered.template_offset(value + 2),
+            template
+                .find("{{")
+                .no_way_because("the template has a placeholder")
+        );
+    }
+
+    #[test]
+    fn escaped_placeholders_are_left_literal() {
+        let rendered = render_template_with("select '\\{{ name }}', x;", &vars(&[]), false)
+            .no_way_because("escaped placeholders need no variable");
+
+        assert_eq!(rendered.text, "select '{{ name }}', x;");
+        assert_eq!(rendered.template_offset(rendered.text.len() - 2), 22);
+    }
+
+    #[test]
+    fn missing_variables_are_reported_with_their_line() {
+        let Err(missing) =
+            render_template_with("select 1;\n\nselect {{ absent }};", &vars(&[]), false)
+        else {
+            panic!("`absent` has no value");
+        };
+
+        assert_eq!(missing.name, "absent");
+        assert_eq!(missing.line, 3);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::abstraction::SupabaseProject;
+
+pub static STATE_DIRECTORY: &str = ".sbp";
+pub static WATCH_STATE_FILENAME: &str = "watch.json";
+pub static WATCH_LOG_FILENAME: &str = "watch.log";
+pub static WATCH_LOCK_FILENAME: &str = "watch.lock";
+
+/// State of watched files kept in `.sbp/watch.json` in the project root
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct WatchState {
+    /// Checksums of files as of their last promotion into a migration, keyed by the path
+    /// relative to the project root
+    pub promoted: BTreeMap<String, String>,
+
+    /// Checksums of files as of their last successful run, keyed the same way
+    pub executed: BTreeMap<String, String>,
+}
+
+impl WatchState {
+    pub fn directory(project: &SupabaseProject) -> PathBuf {
+        project
+            .root
+            .clone()
+            .unwrap_or_default()
+            .join(STATE_DIRECTORY)
+    }
+
+    fn path(project: &SupabaseProject) -> PathBuf {
+        Self::directory(project).join(WATCH_STATE_FILENAME)
+    }
+
+    pub fn load(project: &SupabaseProject) -> anyhow::Result<Self> {
+        let path = Self::path(project);
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path).with_context(|| {
+            styled_error!("Couldn't read {}", (&path.to_string_lossy(), "file_path"))
+        })?;
+
+        serde_json::from_str(&content).with_context(|| {
+            styled_error!(
+                "{} is corrupted, remove it to start over",
+                (&path.to_string_lossy(), "file_path")
+            )
+        })
+    }
+
+    /// Writes the state to a temporary file first and moves it in place, so it's never read
+    /// half-written
+    fn save(&self, project: &SupabaseProject) -> anyhow::Result<()> {
+        let path = Self::path(project);
+        let temporary = path.with_extension(format!("json.{}", std::process::id()));
+
+        std::fs::create_dir_all(Self::directory(project))
+            .and_then(|_| {
+                std::fs::write(
+                    &temporary,
+                    serde_json::to_string_pretty(self).unwrap_or_default() + "\n",
+                )
+            })
+            .and_then(|_| std::fs::rename(&temporary, &path))
+            .with_context(|| {
+                styled_error!("Couldn't write {}", (&path.to_string_lossy(), "file_path"))
+            })
+    }
+
+    /// Exclusive lock over the state shared by `sbp` processes of the project, released once
+    /// the returned file is dropped
+    fn lock(project: &SupabaseProject) -> anyhow::Result<File> {
+        let path = Self::directory(project).join(WATCH_LOCK_FILENAME);
+
+        std::fs::create_dir_all(Self::directory(project))
+            .and_then(|_| {
+                OpenOptions::new()
+                    .create(true)
+                    .truncate(false)
+                    .write(true)
+                    .open(&path)
+            })
+            .and_then(|file| file.lock().map(|_| file))
+            .with_context(|| {
+                styled_error!("Couldn't lock {}", (&path.to_string_lossy(), "file_path"))
+            })
+    }
+
+    /// Applies the change to the freshest state on disk while holding a lock over it, so
+    /// concurrent `sbp` processes don't overwrite each other's entries
+    pub fn update(
+        project: &SupabaseProject,
+        change: impl FnOnce(&mut Self),
+    ) -> anyhow::Result<()> {
+        let _lock = Self::lock(project)?;
+
+        let mut state = Self::load(project)?;
+        change(&mut state);
+        state.save(project)
+    }
+
+    /// Path of the file relative to the project root, falling back to the path as passed
+    pub fn key(project: &SupabaseProject, path: &Path) -> String {
+        let relative = project.root.as_ref().and_then(|root| {
+            let root = std::fs::canonicalize(root).ok()?;
+            let path = std::fs::canonicalize(path).ok()?;
+
+            path.strip_prefix(root).ok().map(Path::to_path_buf)
+        });
+
+        relative
+            .unwrap_or_else(|| path.to_path_buf())
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+pub fn checksum(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Single run of a watched file, appended as a JSON line to `.sbp/watch.log`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExecutionRecord {
+    /// RFC 3339 timestamp
+    pub time: String,
+    pub file: String,
+    pub hash: String,
+    pub error: Option<String>,
+}
+
+impl ExecutionRecord {
+    pub fn new(file: String, hash: String, error: Option<String>) -> Self {
+        Self {
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            file,
+            hash,
+            error,
+        }
+    }
+
+    fn path(project: &SupabaseProject) -> PathBuf {
+        WatchState::directory(project).join(WATCH_LOG_FILENAME)
+    }
+
+    pub fn append(&self, project: &SupabaseProject) -> anyhow::Result<()> {
+        let path = Self::path(project);
+        let line = serde_json::to_string(self).unwrap_or_default();
+
+        std::fs::create_dir_all(WatchState::directory(project))
+            .and_then(|_| {
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+            })
+            .and_then(|mut file| writeln!(file, "{line}"))
+            .with_context(|| {
+                styled_error!("Couldn't write {}", (&path.to_string_lossy(), "file_path"))
+            })
+    }
+
+    /// Every record of the log, oldest first, skipping lines that can't be parsed
+    pub fn read_all(project: &SupabaseProject) -> anyhow::Result<Vec<Self>> {
+        let path = Self::path(project);
+
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = std::fs::read_to_string(&path).with_context(|| {
+            styled_error!("Couldn't read {}", (&path.to_string_lossy(), "file_path"))
+        })?;
+
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
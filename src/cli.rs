@@ -3,10 +3,13 @@ use enum_variant_type::EnumVariantType;
 use evt_trait_object::Variants;
 
 use std::fmt::Debug;
+use std::path::PathBuf;
 
+use crate::commands::config::ConfigCommands;
 use crate::commands::create::CreateCommands;
 use crate::commands::db::DbCommands;
 use crate::commands::manage::ManageCommands;
+use crate::commands::watch::{FileSelection, OutputFormat, WatchCommands};
 
 #[derive(Debug, Parser)]
 #[command(
@@ -33,6 +36,11 @@ pub enum Commands {
         never_write: bool,
     },
 
+    /// Inspect the configuration merged from `sbp.toml` files and `SBP_*` variables
+    #[evt(derive(Debug))]
+    #[command(subcommand)]
+    Config(ConfigCommands),
+
     /// Create a resource of selected type
     #[evt(derive(Debug))]
     #[command(subcommand)]
@@ -43,6 +51,18 @@ pub enum Commands {
     #[command(subcommand)]
     Manage(ManageCommands),
 
+    /// Report which Supabase CLI is used and whether Docker and the project are in place
+    #[evt(derive(Debug, Args))]
+    Doctor {},
+
+    /// Print a SQL file with `{{ var }}` placeholders rendered the way `sbp watch` runs it
+    #[evt(derive(Debug, Args))]
+    Render {
+        /// File to render
+        #[arg()]
+        file: PathBuf,
+    },
+
     /// Stop any running Supabase project
     #[evt(derive(Debug, Args))]
     StopAny {},
@@ -58,12 +78,39 @@ pub enum Commands {
     /// Watch for sql files in a pointed directory and execute them as db queries on change, useful
     /// for storing rpcs in a repository
     #[evt(derive(Debug, Args))]
+    #[command(args_conflicts_with_subcommands = true)]
     Watch {
-        #[arg()]
-        directory: String,
+        #[command(subcommand)]
+        command: Option<WatchCommands>,
+
+        #[command(flatten)]
+        selection: FileSelection,
 
         #[arg(short = 'I', long)]
         immediate: bool,
+
+        /// Drop functions, procedures, views and triggers created by a file before running it,
+        /// so signature changes don't leave stale overloads behind
+        #[arg(long)]
+        autodrop: bool,
+
+        /// Don't wrap files in a transaction, by default a failing file is rolled back as a whole
+        #[arg(long)]
+        no_transaction: bool,
+
+        /// Run files even when unchanged since their last successful run, e.g. after a
+        /// database reset
+        #[arg(long)]
+        force: bool,
+
+        /// Milliseconds to wait for further changes before running changed files together,
+        /// defaults to `watch.debounce` from the config
+        #[arg(long)]
+        debounce: Option<u64>,
+
+        /// Output format, `json` prints newline-delimited events for editor integrations
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
     },
 
     /// Set of commands for direct database operations
@@ -0,0 +1,16 @@
+use crate::cli::{CliSubcommand, Config};
+use clap::{Args, Subcommand};
+use enum_variant_type::EnumVariantType;
+use evt_trait_object::Variants;
+
+handle_subcommands!(Config);
+
+#[derive(Debug, Subcommand, Clone, EnumVariantType, Variants)]
+#[variants_trait(CliSubcommand)]
+pub enum ConfigCommands {
+    /// Print the effective configuration along with the origin of each value
+    #[evt(derive(Args, Debug))]
+    Show {},
+}
+
+mod show;
@@ -0,0 +1,68 @@
+use crate::commands::prelude::*;
+
+use crate::{
+    commands::config::Show,
+    config::{CONFIG, ENV_PREFIX, ENV_PROVIDER_NAME},
+};
+
+use anyhow::Context;
+use figment::Source;
+use serde_json::Value;
+
+fn flatten(prefix: &str, value: Value, entries: &mut Vec<(String, Value)>) {
+    let Value::Object(map) = value else {
+        entries.push((prefix.to_string(), value));
+        return;
+    };
+
+    for (key, value) in map {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        flatten(&key, value, entries);
+    }
+}
+
+#[async_trait]
+impl CliSubcommand for Show {
+    async fn run(self: Box<Self>) -> anyhow::Result<()> {
+        let figment = crate::config::Config::figment();
+
+        let value =
+            serde_json::to_value(&*CONFIG).context("Failed to serialize the configuration")?;
+
+        let mut entries = Vec::new();
+        flatten("", value, &mut entries);
+
+        for (key, value) in entries {
+            let origin = match figment.find_metadata(&key) {
+                None => "default".to_string(),
+                Some(metadata) => match &metadata.source {
+                    Some(Source::File(path)) => path.display().to_string(),
+                    _ if metadata.name == ENV_PROVIDER_NAME => {
+                        format!("{ENV_PREFIX}{}", key.replace('.', "__").to_uppercase())
+                    }
+                    _ => metadata.name.to_string(),
+                },
+            };
+
+            let value = if value.is_null() {
+                "unset".to_string()
+            } else {
+                value.to_string()
+            };
+
+            supercli::styled!(
+                "{} = {} ({})",
+                (key.as_str(), "property"),
+                (value.as_str(), "id"),
+                (origin.as_str(), "dimmed")
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -1,35 +1,139 @@
 use crate::commands::prelude::*;
+use anyhow::Context;
 
 use crate::{
-    abstraction::{NewBucket, SupabaseProject},
+    abstraction::{BucketDraft, NewBucket, NewStoragePolicy, SupabaseProject},
     commands::create::Bucket,
+    config::CONFIG,
+    sys::is_interactive,
+    utils::parse_size,
 };
 
+impl Bucket {
+    fn draft(&self) -> anyhow::Result<BucketDraft> {
+        let mut mime_types = self.mime_types.clone();
+
+        for extension in &self.extensions {
+            let Some(mime_type) = NewBucket::guess_mime_type(extension) else {
+                crate::styled_bail!(
+                    "Couldn't guess the mime type of `{}` extension, pass it with `{}` instead",
+                    (extension, "id"),
+                    ("--mime-type", "command")
+                );
+            };
+
+            mime_types.push(mime_type);
+        }
+
+        let file_size_limit = match &self.file_size_limit {
+            Some(limit) => Some(parse_size(limit).with_context(|| {
+                styled_error!(
+                    "Invalid file size limit `{}`, use a number optionally followed by a unit, e.g. `{}`",
+                    (limit, "id"),
+                    ("5MB", "id")
+                )
+            })?),
+            None => None,
+        };
+
+        let public = if self.public {
+            Some(true)
+        } else if self.private {
+            Some(false)
+        } else {
+            None
+        };
+
+        Ok(BucketDraft {
+            name: self.name.clone(),
+            public,
+            mime_types,
+            file_size_limit,
+            avif_autodetection: self.avif_autodetection,
+            upsert: self.upsert,
+        })
+    }
+
+    fn apply(&self) -> Option<bool> {
+        if self.apply {
+            Some(true)
+        } else if self.no_apply {
+            Some(false)
+        } else {
+            CONFIG.migrations.auto_apply
+        }
+    }
+}
+
 #[async_trait]
 impl CliSubcommand for Bucket {
     async fn run(self: Box<Self>) -> anyhow::Result<()> {
         let project = SupabaseProject::from_cwd().await?;
 
-        let (bucket, shall_run) = use_promptuity!(promptuity => {
-            let Ok(bucket) = NewBucket::new_interactively(&mut promptuity, project.id()) else {
-                return Ok(());
+        let draft = self.draft()?;
+        let apply = self.apply();
+
+        let (bucket, policy, shall_run) = if !is_interactive() {
+            let bucket = NewBucket::from_draft(draft)?;
+
+            let Some(shall_run) = apply else {
+                crate::styled_bail!(
+                    "Pass `{}` or `{}` to decide whether to run the migration immediately",
+                    ("--apply", "command"),
+                    ("--no-apply", "command")
+                );
             };
 
-            let shall_run = promptuity
-                .prompt(
-                    Confirm::new(
-                        "Would you like to run this migration immediately and set it to applied?",
+            (bucket, None, shall_run)
+        } else if let Some(shall_run) =
+            apply.filter(|_| draft.name.is_some() && draft.public.is_some())
+        {
+            (NewBucket::from_draft(draft)?, None, shall_run)
+        } else {
+            use_promptuity!(promptuity => {
+                let Ok(bucket) = NewBucket::new_interactively(&mut promptuity, project.id(), draft) else {
+                    return Ok(());
+                };
+
+                let shall_run = match apply {
+                    Some(shall_run) => shall_run,
+                    None => promptuity
+                        .prompt(
+                            Confirm::new(
+                                "Would you like to run this migration immediately and set it to applied?",
+                            )
+                            .with_default(true),
+                        )
+                        .unwrap_or_else(|_| exit(0)),
+                };
+
+                // The bucket is settled by now, so cancelling any of the policy prompts still
+                // gets its migration written, just without the policies
+                let with_policy = promptuity
+                    .prompt(
+                        Confirm::new("Would you also like to create storage policies for it?")
+                            .with_default(false),
                     )
-                    .with_default(true),
-                )
-                .unwrap_or_else(|_| exit(0));
+                    .unwrap_or_default();
+
+                let policy = if with_policy {
+                    NewStoragePolicy::prompt_for_bucket(&mut promptuity, bucket.name.clone()).ok()
+                } else {
+                    None
+                };
 
-            let _ = promptuity.finish();
+                let _ = promptuity.finish();
 
-            (bucket, shall_run)
-        });
+                (bucket, policy, shall_run)
+            })
+        };
 
         project.create_migration(bucket, shall_run, shall_run).await?;
+
+        if let Some(policy) = policy {
+            project.create_migration(policy, shall_run, shall_run).await?;
+        }
+
         supercli::success!("Migration file created successfully!");
 
         Ok(())
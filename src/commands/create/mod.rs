@@ -9,9 +9,59 @@ handle_subcommands!(Create);
 #[variants_trait(CliSubcommand)]
 pub enum CreateCommands {
     /// Create a new bucket interactively, by creating a migration file inserting record into
-    /// "storage"."buckets"
+    /// "storage"."buckets", values passed as flags aren't prompted for
     #[evt(derive(Args, Debug))]
-    Bucket {},
+    Bucket {
+        /// Slug of the bucket
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Make the bucket public
+        #[arg(long, conflicts_with = "private")]
+        public: bool,
+
+        /// Make the bucket private
+        #[arg(long)]
+        private: bool,
+
+        /// Accepted mime type, can be repeated
+        #[arg(long = "mime-type", value_name = "MIME_TYPE")]
+        mime_types: Vec<String>,
+
+        /// File extension to guess an accepted mime type from, can be repeated
+        #[arg(long = "extension", value_name = "EXTENSION")]
+        extensions: Vec<String>,
+
+        /// Max size of a single file, in bytes or with a unit, e.g. `5MB`, `512KiB`
+        #[arg(long)]
+        file_size_limit: Option<String>,
+
+        /// Let Supabase detect AVIF images by their content
+        #[arg(long)]
+        avif_autodetection: bool,
+
+        /// Reconcile the bucket if it already exists instead of failing (`ON CONFLICT DO UPDATE`)
+        #[arg(long)]
+        upsert: bool,
+
+        /// Run the migration immediately and set it to applied
+        #[arg(long, conflicts_with = "no_apply")]
+        apply: bool,
+
+        /// Only create the migration file
+        #[arg(long)]
+        no_apply: bool,
+    },
+
+    /// Create row level security policies on "storage"."objects" for a bucket interactively,
+    /// by creating a migration file
+    #[evt(derive(Args, Debug))]
+    StoragePolicy {
+        /// Bucket the policies apply to, asked for if not passed
+        #[arg(long)]
+        bucket: Option<String>,
+    },
 }
 
 mod bucket;
+mod storage_policy;
@@ -0,0 +1,66 @@
+use crate::commands::prelude::*;
+
+use crate::{
+    abstraction::{NewStoragePolicy, SupabaseProject},
+    commands::create::StoragePolicy,
+    config::CONFIG,
+};
+
+#[async_trait]
+impl CliSubcommand for StoragePolicy {
+    async fn run(self: Box<Self>) -> anyhow::Result<()> {
+        let project = SupabaseProject::from_cwd().await?;
+
+        let buckets = project
+            .buckets()
+            .await?
+            .into_iter()
+            .map(|bucket| bucket.id)
+            .collect::<Vec<_>>();
+
+        if buckets.is_empty() {
+            crate::styled_bail!(
+                "You don't seem to have any buckets, create one with `{}`",
+                ("sbp create bucket", "command")
+            );
+        }
+
+        if let Some(bucket) = &self.bucket {
+            if !buckets.contains(bucket) {
+                crate::styled_bail!("There's no `{}` bucket", (bucket, "id"));
+            }
+        }
+
+        let (policy, shall_run) = use_promptuity!(promptuity => {
+            let Ok(policy) = NewStoragePolicy::new_interactively(
+                &mut promptuity,
+                buckets,
+                self.bucket,
+                project.id()
+            ) else {
+                return Ok(());
+            };
+
+            let shall_run = match CONFIG.migrations.auto_apply {
+                Some(auto_apply) => auto_apply,
+                None => promptuity
+                    .prompt(
+                        Confirm::new(
+                            "Would you like to run this migration immediately and set it to applied?",
+                        )
+                        .with_default(true),
+                    )
+                    .unwrap_or_else(|_| exit(0)),
+            };
+
+            let _ = promptuity.finish();
+
+            (policy, shall_run)
+        });
+
+        project.create_migration(policy, shall_run, shall_run).await?;
+        supercli::success!("Migration file created successfully!");
+
+        Ok(())
+    }
+}
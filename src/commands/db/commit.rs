@@ -1,6 +1,11 @@
 use crate::abstraction::DbDiffError;
+use crate::config::CONFIG;
 use crate::errors::NoWay;
-use crate::{abstraction::SupabaseProject, commands::db::Commit};
+use crate::sys::is_interactive;
+use crate::{
+    abstraction::SupabaseProject,
+    commands::db::{Commit, entities::missing_entities, guard::guard, review::review},
+};
 
 use crate::commands::prelude::*;
 use crate::patched::throbberous::Throbber;
@@ -9,66 +14,136 @@ use heck::ToKebabCase;
 use std::process::exit;
 use tokio::sync::oneshot;
 
+// Exit codes start at 3, as 1 is used for errors in general and 2 by `clap` for invalid usage
+
+/// Exit code when there are no changes to commit
+pub static EXIT_NO_CHANGES: i32 = 3;
+
+/// Exit code when `supabase db diff` fails
+pub static EXIT_DIFF_FAILED: i32 = 4;
+
+/// Exit code when destructive changes haven't been allowed
+pub static EXIT_DESTRUCTIVE: i32 = 5;
+
+/// Exit code when a prompt or `supabase db diff` has been cancelled
+pub static EXIT_CANCELLED: i32 = 6;
+
+static DEFAULT_MESSAGE: &str = "Commited changes";
+
+/// Diff along with the reason why the native diff fell back to the CLI, if it did
+type DiffOutput = (
+    anyhow::Result<Option<String>, DbDiffError>,
+    Option<anyhow::Error>,
+);
+
 #[async_trait]
 impl CliSubcommand for Commit {
     async fn run(self: Box<Self>) -> anyhow::Result<()> {
-        let Commit { schema } = *self;
+        let Commit {
+            schema,
+            all_user_schemas,
+            schema_only,
+            native,
+            allow_destructive,
+            name,
+            yes,
+        } = *self;
+
+        if name.is_none() && !yes && !is_interactive() {
+            crate::styled_bail!(
+                "Pass the migration name with `{}` or accept the default one with `{}`",
+                ("--name", "command"),
+                ("--yes", "command")
+            );
+        }
+
         let project = SupabaseProject::from_cwd().await?;
 
-        let (tx, rx) = oneshot::channel::<anyhow::Result<Option<String>, DbDiffError>>();
+        let schemas = if all_user_schemas {
+            project.user_schemas().await?
+        } else if schema.is_empty() {
+            vec![CONFIG.db.schema.clone()]
+        } else {
+            schema
+        };
+
+        if schemas.is_empty() {
+            supercli::info!(" No user schemas found. Nothing to commit.");
+            exit(EXIT_NO_CHANGES);
+        }
+
+        let native = native || CONFIG.diff.native;
+
+        let (tx, rx) = oneshot::channel::<DiffOutput>();
 
         tokio::spawn({
             let project = project.clone();
+            let schemas = schemas.clone();
 
             async move {
-                let output = project.db_diff(&schema).await;
+                let output = if native {
+                    match project.native_db_diff(&schemas).await {
+                        Ok(sql) => (Ok(sql), None),
+                        Err(error) => (project.db_diff(&schemas).await, Some(error)),
+                    }
+                } else {
+                    (project.db_diff(&schemas).await, None)
+                };
+
                 tx.send(output).no_way_because("`oneshot` just created");
             }
         });
 
-        let message = use_promptuity!(promptuity => {
-            promptuity
-                .with_intro(&format!("Committing changes ({})", project.id()))
-                .begin()
-                .context("Failed to start interactive mode")?;
-
-            let message = promptuity.prompt(
-                Input::new("How would you like to name this migration?")
-                    .with_hint("press enter to sustain default name")
-                    .with_placeholder("Commited changes")
-                    .with_required(false)
-                    .with_transformer(|value: &str| {
-                        if value.is_empty() {
-                            "commited-changes".into()
-                        } else {
-                            value.to_kebab_case()
-                        }
-                    }),
-            );
-
-            match message.ok() {
-                Some(message) => {
-                    let _ = promptuity.finish();
-                    Some(
-                        (!message.is_empty())
-                            .then_some(message)
-                            .unwrap_or("Commited changes".into()),
-                    )
+        let message = if let Some(name) = name {
+            Some(name)
+        } else if yes {
+            Some(DEFAULT_MESSAGE.to_string())
+        } else {
+            use_promptuity!(promptuity => {
+                promptuity
+                    .with_intro(&format!("Committing changes ({})", project.id()))
+                    .begin()
+                    .context("Failed to start interactive mode")?;
+
+                let message = promptuity.prompt(
+                    Input::new("How would you like to name this migration?")
+                        .with_hint("press enter to sustain default name")
+                        .with_placeholder("Commited changes")
+                        .with_required(false)
+                        .with_transformer(|value: &str| {
+                            if value.is_empty() {
+                                "commited-changes".into()
+                            } else {
+                                value.to_kebab_case()
+                            }
+                        }),
+                );
+
+                match message.ok() {
+                    Some(message) => {
+                        let _ = promptuity.finish();
+                        Some(
+                            (!message.is_empty())
+                                .then_some(message)
+                                .unwrap_or(DEFAULT_MESSAGE.into()),
+                        )
+                    }
+                    _ => None,
                 }
-                _ => None,
-            }
-        });
+            })
+        };
 
         let Some(message) = message else {
             project.kill_shadow_db().await?;
-            exit(0);
+            exit(EXIT_CANCELLED);
         };
 
         let throbber = Throbber::new();
         throbber.set_message(" awaiting `db diff`…").await;
         throbber.start().await;
 
-        let output = &rx.await?;
+        let (output, fallback) = rx.await?;
+        let output = &output;
 
         let Ok(sql) = output else {
             let error = output
@@ -78,27 +153,88 @@ impl CliSubcommand for Commit {
 
             if let DbDiffError::Terminated = error {
                 throbber.stop_err(" terminated").await;
-                exit(0);
+                exit(EXIT_CANCELLED);
             };
 
             throbber.stop_success(" `db diff` has run").await;
 
-            styled_bail!(
-                "Couldn't generate diff, stderr from `{}`:\n> {}",
+            supercli::error!(&styled_error!(
+                "Error: Couldn't generate diff, stderr from `{}`:\n> {}",
                 ("supabase db diff", "command"),
                 (error.to_string(), "muted")
-            )
+            ));
+
+            exit(EXIT_DIFF_FAILED);
         };
 
         throbber.stop_success(" `db diff` completed").await;
 
-        let Some(sql) = sql else {
+        // The shadow database kept running by the native diff holds what migrations produce
+        let warm = native && fallback.is_none();
+
+        if let Some(error) = fallback {
+            supercli::styled!(
+                "Native diff fell back to `{}`\n> {}",
+                ("supabase db diff", "command"),
+                (&format!("{:#}", error), "muted")
+            );
+        }
+
+        let entities = if schema_only {
+            None
+        } else {
+            let throbber = Throbber::new();
+            throbber
+                .set_message(" comparing buckets and realtime with the migrations…")
+                .await;
+            throbber.start().await;
+
+            match missing_entities(&project, &schemas, warm).await {
+                Ok(entities) => {
+                    throbber.stop_success(" buckets and realtime compared").await;
+                    entities
+                }
+                Err(error) => {
+                    throbber.stop_err(" couldn't compare buckets and realtime").await;
+
+                    supercli::styled!(
+                        "Only changes detected by `{}` will be committed, pass `{}` to skip the comparison\n> {}",
+                        ("supabase db diff", "command"),
+                        ("--schema-only", "command"),
+                        (&format!("{:#}", error), "muted")
+                    );
+
+                    None
+                }
+            }
+        };
+
+        let sql = [sql.clone(), entities]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if sql.is_empty() {
             supercli::info!(" No changes detected in the schema. Nothing to commit.");
-            return Ok(());
+            exit(EXIT_NO_CHANGES);
+        }
+
+        let sql = if yes || !is_interactive() {
+            sql
+        } else {
+            let Some(sql) = review(project.id(), &sql)? else {
+                supercli::info!(" All statements have been deselected. Nothing to commit.");
+                exit(EXIT_NO_CHANGES);
+            };
+
+            sql
         };
 
+        guard(project.id(), &sql, allow_destructive, yes)?;
+
         project
-            .create_migration((sql.to_owned(), message), false, true)
+            .create_migration((sql, message), false, true)
             .await?;
 
         supercli::success!(" Changes have been committed to the migration directory!");
@@ -0,0 +1,123 @@
+use crate::abstraction::{
+    BucketChange, Migration, NewBucket, PublishedTable, REALTIME_PUBLICATION, RealtimeChange,
+    ShadowDb, StorageBucket, SupabaseProject,
+};
+
+/// What the migrations produce for entities `db diff` doesn't see
+struct Replayed {
+    buckets: Vec<StorageBucket>,
+    realtime: Option<Vec<PublishedTable>>,
+}
+
+/// Statements bringing buckets and `supabase_realtime` membership produced by the migrations
+/// (replayed on the shadow database) in line with the local database, `None` when they match,
+/// `warm` reuses the shadow database the native diff has just brought up to date instead of
+/// replaying every migration on a fresh one
+pub async fn missing_entities(
+    project: &SupabaseProject,
+    schemas: &[String],
+    warm: bool,
+) -> anyhow::Result<Option<String>> {
+    let buckets = project.buckets().await?;
+    let realtime = published(project, schemas).await?;
+
+    let replayed = if warm {
+        replayed(&ShadowDb::warm(project).await?, schemas).await?
+    } else {
+        let shadow = ShadowDb::start(project).await?;
+        let replayed = replay(&shadow, project, schemas).await;
+        shadow.stop().await?;
+
+        replayed?
+    };
+    let mut sections = Vec::new();
+
+    let bucket_changes = bucket_changes(replayed.buckets, buckets);
+
+    if !bucket_changes.is_empty() {
+        sections.push(format!("-- Storage buckets\n\n{}", bucket_changes.join("\n")));
+    }
+
+    if let Some(desired) = realtime {
+        let change = RealtimeChange::diff(
+            REALTIME_PUBLICATION,
+            replayed.realtime.is_none(),
+            replayed.realtime.unwrap_or_default(),
+            desired,
+        );
+
+        if !change.is_empty() {
+            sections.push(format!("-- Realtime\n\n{}\n", change.sql()));
+        }
+    }
+
+    Ok((!sections.is_empty()).then(|| sections.join("\n")))
+}
+
+async fn published(
+    project: &SupabaseProject,
+    schemas: &[String],
+) -> anyhow::Result<Option<Vec<PublishedTable>>> {
+    if !project.publication_exists(REALTIME_PUBLICATION).await? {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        project
+            .publication_tables(REALTIME_PUBLICATION, schemas)
+            .await?,
+    ))
+}
+
+async fn replay(
+    shadow: &ShadowDb,
+    project: &SupabaseProject,
+    schemas: &[String],
+) -> anyhow::Result<Replayed> {
+    shadow.replay(project).await?;
+
+    replayed(shadow, schemas).await
+}
+
+async fn replayed(shadow: &ShadowDb, schemas: &[String]) -> anyhow::Result<Replayed> {
+    Ok(Replayed {
+        buckets: shadow.project.buckets().await?,
+        realtime: published(&shadow.project, schemas).await?,
+    })
+}
+
+fn bucket_changes(replayed: Vec<StorageBucket>, local: Vec<StorageBucket>) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    for bucket in &local {
+        match replayed.iter().find(|other| other.id == bucket.id) {
+            Some(other) if other == bucket => {}
+            Some(other) => changes.push(
+                BucketChange::Update {
+                    before: other.clone(),
+                    after: bucket.clone(),
+                }
+                .sql(),
+            ),
+            None => changes.push(
+                NewBucket {
+                    name: bucket.id.clone(),
+                    public: bucket.public,
+                    mime_types: bucket.mime_types.clone(),
+                    file_size_limit: bucket.file_size_limit,
+                    avif_autodetection: bucket.avif_autodetection,
+                    upsert: true,
+                }
+                .sql(),
+            ),
+        }
+    }
+
+    for bucket in replayed {
+        if !local.iter().any(|other| other.id == bucket.id) {
+            changes.push(BucketChange::Delete(bucket).sql());
+        }
+    }
+
+    changes
+}
@@ -0,0 +1,172 @@
+use std::io::Read;
+
+use crate::commands::prelude::*;
+
+use anyhow::Context;
+use serde_json::{Map, Value};
+
+use crate::{
+    abstraction::{ResultSet, SupabaseProject},
+    commands::db::{Exec, ResultFormat},
+};
+
+impl Exec {
+    fn script(&self) -> anyhow::Result<String> {
+        if let Some(command) = &self.command {
+            return Ok(command.clone());
+        }
+
+        match self.file.as_deref() {
+            Some("-") | None => {
+                let mut script = String::new();
+
+                std::io::stdin()
+                    .read_to_string(&mut script)
+                    .context("Failed to read SQL from stdin")?;
+
+                Ok(script)
+            }
+            Some(file) => std::fs::read_to_string(file).with_context(|| {
+                styled_error!(
+                    "Make sure the file at {} exists and is a valid UTF-8 file",
+                    (file, "file_path")
+                )
+            }),
+        }
+    }
+}
+
+fn print_table(columns: &[String], rows: &[Vec<Option<String>>]) {
+    let cell = |value: &Option<String>| value.clone().unwrap_or_else(|| "NULL".into());
+
+    let widths = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            rows.iter()
+                .map(|row| cell(&row[index]).chars().count())
+                .chain([column.chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let line = |values: Vec<String>| {
+        values
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{value:<width$}"))
+            .collect::<Vec<_>>()
+            .join(" │ ")
+    };
+
+    println!("{}", line(columns.to_vec()));
+    println!(
+        "{}",
+        widths
+            .iter()
+            .map(|width| "─".repeat(*width))
+            .collect::<Vec<_>>()
+            .join("─┼─")
+    );
+
+    for row in rows {
+        println!("{}", line(row.iter().map(cell).collect()));
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn print_csv(columns: &[String], rows: &[Vec<Option<String>>]) {
+    let line = |values: Vec<String>| {
+        values
+            .iter()
+            .map(|value| csv_field(value))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    println!("{}", line(columns.to_vec()));
+
+    for row in rows {
+        println!("{}", line(row.iter().map(|value| value.clone().unwrap_or_default()).collect()));
+    }
+}
+
+fn print_json(columns: &[String], rows: &[Vec<Option<String>>]) {
+    let rows = rows
+        .iter()
+        .map(|row| {
+            Value::Object(
+                columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, value)| {
+                        (
+                            column.clone(),
+                            value.clone().map(Value::String).unwrap_or(Value::Null),
+                        )
+                    })
+                    .collect::<Map<_, _>>(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    println!("{}", Value::Array(rows));
+}
+
+#[async_trait]
+impl CliSubcommand for Exec {
+    async fn run(self: Box<Self>) -> anyhow::Result<()> {
+        let script = self.script()?;
+        let project = SupabaseProject::from_cwd().await?;
+
+        let results = project
+            .runtime()
+            .exec(&script, !self.no_transaction)
+            .await?;
+
+        let mut first = true;
+
+        for ResultSet {
+            columns,
+            rows,
+            affected,
+        } in results
+        {
+            let Some(columns) = columns else {
+                if self.format == ResultFormat::Table {
+                    supercli::styled!(
+                        "{}",
+                        (&format!("{affected} rows affected"), "muted")
+                    );
+                }
+
+                continue;
+            };
+
+            if !first && self.format != ResultFormat::Json {
+                println!();
+            }
+
+            first = false;
+
+            match self.format {
+                ResultFormat::Table => {
+                    print_table(&columns, &rows);
+                    supercli::styled!("{}", (&format!("({} rows)", rows.len()), "muted"));
+                }
+                ResultFormat::Csv => print_csv(&columns, &rows),
+                ResultFormat::Json => print_json(&columns, &rows),
+            }
+        }
+
+        Ok(())
+    }
+}
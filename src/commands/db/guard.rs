@@ -0,0 +1,100 @@
+use crate::commands::prelude::*;
+
+use anyhow::Context;
+use supercli::output::styling::apply_style;
+
+use crate::{
+    abstraction::{Risk, highlight, split_statements},
+    commands::db::commit::EXIT_DESTRUCTIVE,
+    sys::is_interactive,
+};
+
+/// Summarizes statements of the migration that aren't safe to run against a database with data,
+/// destructive ones have to be confirmed or allowed with `--allow-destructive`
+pub fn guard(
+    project_id: &str,
+    sql: &str,
+    allow_destructive: bool,
+    yes: bool,
+) -> anyhow::Result<()> {
+    let statements = split_statements(sql);
+    let risks = statements
+        .iter()
+        .map(|statement| statement.risk())
+        .collect::<Vec<_>>();
+
+    let count = |risk: Risk| risks.iter().filter(|(other, _)| *other == risk).count();
+    let destructive = count(Risk::Destructive);
+    let backfill = count(Risk::RequiresBackfill);
+
+    if destructive == 0 && backfill == 0 {
+        return Ok(());
+    }
+
+    let mut summary = format!(
+        "{} safe, {} requiring backfill, {} destructive statements",
+        count(Risk::Safe),
+        backfill,
+        destructive
+    );
+
+    for (statement, (risk, reason)) in statements.iter().zip(&risks) {
+        let Some(reason) = reason else {
+            continue;
+        };
+
+        summary.push_str(&format!(
+            "\n\n{} line {}, {}\n",
+            apply_style(&risk.to_string().to_uppercase(), "error"),
+            apply_style(&statement.locate(sql, 1).0.to_string(), "number"),
+            apply_style(reason, "muted")
+        ));
+        summary.push_str(&highlight(&statement.text));
+    }
+
+    if destructive == 0 || allow_destructive {
+        eprintln!("{}\n", summary);
+        return Ok(());
+    }
+
+    if yes || !is_interactive() {
+        eprintln!("{}\n", summary);
+
+        supercli::error!(&styled_error!(
+            "Error: The migration contains destructive changes, review them and pass `{}` to commit anyway",
+            ("--allow-destructive", "command")
+        ));
+
+        exit(EXIT_DESTRUCTIVE);
+    }
+
+    let confirmed = use_promptuity!(promptuity => {
+        promptuity
+            .with_intro(&format!("Destructive changes ({})", project_id))
+            .begin()
+            .context("Failed to start interactive mode")?;
+
+        promptuity
+            .warn(summary)
+            .context("Failed to show the summary")?;
+
+        let confirmed = promptuity
+            .prompt(
+                Confirm::new("Would you like to commit them anyway?")
+                    .with_hint("data will be lost once the migration runs")
+                    .with_default(false),
+            )
+            .unwrap_or_else(|_| exit(EXIT_DESTRUCTIVE));
+
+        let _ = promptuity.finish();
+
+        confirmed
+    });
+
+    if !confirmed {
+        supercli::info!(" Nothing has been committed.");
+        exit(EXIT_DESTRUCTIVE);
+    }
+
+    Ok(())
+}
@@ -11,9 +11,72 @@ pub enum DbCommands {
     /// Creates new migration containing all changes made to local schema
     #[evt(derive(Debug, Args))]
     Commit {
-        #[arg(long, short, default_value = "public")]
-        schema: String,
+        /// Schemas to diff, comma separated or repeated, defaults to `db.schema` from the config
+        #[arg(long, short, value_delimiter = ',', conflicts_with = "all_user_schemas")]
+        schema: Vec<String>,
+
+        /// Diff every schema but the system ones and those managed by Supabase
+        #[arg(long)]
+        all_user_schemas: bool,
+
+        /// Name of the migration, skips the prompt
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Only commit changes detected by `supabase db diff`, without comparing buckets and
+        /// realtime publication membership with what the migrations produce
+        #[arg(long)]
+        schema_only: bool,
+
+        /// Diff catalogs against a warm shadow database instead of running `supabase db diff`,
+        /// falling back to it when unsupported objects have changed
+        #[arg(long)]
+        native: bool,
+
+        /// Commit changes losing data (e.g. dropped tables or columns) without confirmation
+        #[arg(long)]
+        allow_destructive: bool,
+
+        /// Don't prompt nor review the changes, using the default name unless `--name` is passed
+        #[arg(long, short)]
+        yes: bool,
+    },
+
+    /// Run a SQL file, stdin or a command against the local database and print returned rows
+    #[evt(derive(Debug, Args))]
+    Exec {
+        /// File to run, `-` for stdin
+        #[arg(required_unless_present = "command", conflicts_with = "command")]
+        file: Option<String>,
+
+        /// SQL to run instead of a file
+        #[arg(long, short)]
+        command: Option<String>,
+
+        /// Format of returned rows
+        #[arg(long, value_enum, default_value_t)]
+        format: ResultFormat,
+
+        /// Don't wrap the statements in a transaction
+        #[arg(long)]
+        no_transaction: bool,
     },
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ResultFormat {
+    /// Aligned columns
+    #[default]
+    Table,
+
+    Csv,
+
+    /// An array of row objects per statement, one per line
+    Json,
+}
+
 mod commit;
+mod entities;
+mod exec;
+mod guard;
+mod review;
@@ -0,0 +1,171 @@
+use std::{io::Write, path::Path};
+
+use crate::commands::prelude::*;
+
+use anyhow::Context;
+use duct::cmd;
+
+use crate::{
+    abstraction::{SqlStatement, highlight, split_statements},
+    commands::db::commit::EXIT_CANCELLED,
+};
+
+/// Statements' labels are cut to this many characters in the checklist
+static LABEL_WIDTH: usize = 72;
+
+/// Shows the generated migration and lets the user deselect its statements and edit the rest
+/// in `$VISUAL`/`$EDITOR`, `None` when nothing is left to commit
+pub fn review(project_id: &str, sql: &str) -> anyhow::Result<Option<String>> {
+    let statements = split_statements(sql);
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty());
+
+    let (selected, edit) = use_promptuity!(promptuity => {
+        promptuity
+            .with_intro(&format!("Reviewing migration ({})", project_id))
+            .begin()
+            .context("Failed to start interactive mode")?;
+
+        promptuity
+            .log(highlight(sql.trim_end()))
+            .context("Failed to show the migration")?;
+
+        let selected = if statements.len() > 1 {
+            let Ok(selected) = promptuity.prompt(
+                MultiSelect::new(
+                    "Which statements should be committed?",
+                    statements
+                        .iter()
+                        .enumerate()
+                        .map(|(index, statement)| MultiSelectOption {
+                            label: label(statement),
+                            value: index,
+                            selected: true,
+                            hint: Some(format!("line {}", statement.locate(sql, 1).0)),
+                        })
+                        .collect(),
+                )
+                .with_required(false)
+                .with_hint("deselect e.g. accidental grants or drops")
+                .as_mut(),
+            ) else {
+                exit(EXIT_CANCELLED);
+            };
+
+            selected
+        } else {
+            (0..statements.len()).collect()
+        };
+
+        let edit = match &editor {
+            Some(editor) => {
+                let hint = format!("opens `{}`", editor);
+
+                promptuity
+                    .prompt(
+                        Confirm::new("Would you like to edit the migration before committing?")
+                            .with_hint(&hint)
+                            .with_default(false),
+                    )
+                    .unwrap_or_else(|_| exit(EXIT_CANCELLED))
+            }
+            None => false,
+        };
+
+        let _ = promptuity.finish();
+
+        (selected, edit)
+    });
+
+    let mut kept = statements
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| selected.contains(index))
+        .map(|(_, statement)| format!("{};\n", statement.text))
+        .collect::<Vec<_>>();
+
+    // Comments after the last statement aren't part of any, they're kept as they are
+    if let Some(trailing) = trailing_comments(sql, &statements) {
+        kept.push(format!("{}\n", trailing));
+    }
+
+    let sql = kept.join("\n");
+
+    let sql = match editor.filter(|_| edit) {
+        Some(editor) => edit_in(&editor, &sql)?,
+        None => sql,
+    };
+
+    Ok((!split_statements(&sql).is_empty()).then_some(sql))
+}
+
+/// First line of the statement's code, skipping leading comments
+fn label(statement: &SqlStatement) -> String {
+    let code = statement.code();
+    let line = code
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+
+    if line.chars().count() > LABEL_WIDTH {
+        format!("{}…", line.chars().take(LABEL_WIDTH - 1).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// Whatever follows the last statement of the script, e.g. a closing comment
+fn trailing_comments<'a>(sql: &'a str, statements: &[SqlStatement]) -> Option<&'a str> {
+    let last = statements.last()?;
+    let rest = sql[last.offset + last.text.len()..].trim_start();
+    let rest = rest.strip_prefix(';').unwrap_or(rest).trim();
+
+    (!rest.is_empty()).then_some(rest)
+}
+
+/// Opens the migration in the editor through a temporary file, created with a random name
+/// and removed once it's dropped
+fn edit_in(editor: &str, sql: &str) -> anyhow::Result<String> {
+    let mut file = tempfile::Builder::new()
+        .prefix("sbp-migration-")
+        .suffix(".sql")
+        .tempfile()
+        .context("Failed to create a temporary file for the migration")?;
+
+    file.write_all(sql.as_bytes())
+        .and_then(|_| file.flush())
+        .with_context(|| {
+            styled_error!(
+                "Failed to write the migration to {}",
+                (&file.path().display().to_string(), "file_path")
+            )
+        })?;
+
+    run_editor(editor, file.path())
+}
+
+fn run_editor(editor: &str, path: &Path) -> anyhow::Result<String> {
+    let command = format!(
+        "{} \"{}\"",
+        editor,
+        escape_for_sh_double_quotes(&path.display().to_string())
+    );
+
+    if let Err(error) = cmd!("sh", "-c", &command).run() {
+        crate::styled_bail!(
+            "Editor `{}` failed:\n> {}",
+            (editor, "command"),
+            (&format!("{:?}", error), "muted")
+        );
+    }
+
+    std::fs::read_to_string(path).with_context(|| {
+        styled_error!(
+            "Failed to read the edited migration from {}",
+            (&path.display().to_string(), "file_path")
+        )
+    })
+}
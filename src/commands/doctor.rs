@@ -0,0 +1,61 @@
+use super::prelude::*;
+use crate::abstraction::{SupabaseCli, SupabaseProject};
+
+#[async_trait]
+impl CliSubcommand for Doctor {
+    async fn run(self: Box<Self>) -> anyhow::Result<()> {
+        match SupabaseCli::current() {
+            Ok(cli) => {
+                supercli::styled!(
+                    "{} Supabase CLI is invoked with `{}` ({} source)",
+                    ("✔", "success_symbol"),
+                    (cli.invocation(), "command"),
+                    (cli.to_string(), "id")
+                );
+
+                match cli.version().await {
+                    Ok(version) => supercli::styled!("  version {}", (version, "number")),
+                    Err(error) => supercli::styled!(
+                        "  {} it failed to report its version\n  > {}",
+                        ("✖", "error"),
+                        (error.to_string(), "dimmed")
+                    ),
+                }
+            }
+            Err(error) => supercli::styled!("{} {}", ("✖", "error"), (error.to_string(), "error")),
+        }
+
+        match SupabaseProject::running().await {
+            Ok(projects) => {
+                supercli::styled!(
+                    "{} Docker is reachable, running projects: {}",
+                    ("✔", "success_symbol"),
+                    (projects.len().to_string(), "number")
+                );
+
+                for project in projects {
+                    supercli::styled!("  `{}`", (project.id(), "id"));
+                }
+            }
+            Err(error) => supercli::styled!("{} {}", ("✖", "error"), (error.to_string(), "error")),
+        }
+
+        match SupabaseProject::find() {
+            Ok(project) => {
+                let config = &project.config;
+
+                supercli::styled!(
+                    "{} Project `{}` found, api port {}, db port {}, shadow db port {}",
+                    ("✔", "success_symbol"),
+                    (project.id(), "id"),
+                    (config.api.port.to_string(), "number"),
+                    (config.db.port.to_string(), "number"),
+                    (config.db.shadow_port.to_string(), "number")
+                );
+            }
+            Err(error) => supercli::styled!("{} {}", ("✖", "error"), (error.to_string(), "error")),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use crate::commands::prelude::*;
+
+use crate::{
+    abstraction::{BucketChange, SupabaseProject},
+    commands::manage::Buckets,
+    config::CONFIG,
+};
+
+#[async_trait]
+impl CliSubcommand for Buckets {
+    async fn run(self: Box<Self>) -> anyhow::Result<()> {
+        let project = SupabaseProject::from_cwd().await?;
+
+        let buckets = project.buckets().await?;
+
+        if buckets.is_empty() {
+            crate::styled_bail!("You don't seem to have any buckets");
+        }
+
+        let (bucket_change, shall_run) = use_promptuity!(promptuity => {
+            let Ok(bucket_change) = BucketChange::new_interactively(
+                &mut promptuity,
+                buckets,
+                project.id()
+            ) else {
+                return Ok(());
+            };
+
+            let shall_run = match CONFIG.migrations.auto_apply {
+                Some(auto_apply) => auto_apply,
+                None => promptuity
+                    .prompt(
+                        Confirm::new(
+                            "Would you like to run this migration immediately and set it to applied?",
+                        )
+                        .with_default(true),
+                    )
+                    .unwrap_or_else(|_| exit(0)),
+            };
+
+            let _ = promptuity.finish();
+
+            (bucket_change, shall_run)
+        });
+
+        project
+            .create_migration(bucket_change, shall_run, shall_run)
+            .await?;
+        supercli::success!("Migration file created successfully!");
+
+        Ok(())
+    }
+}
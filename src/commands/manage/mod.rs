@@ -8,11 +8,38 @@ handle_subcommands!(Manage);
 #[derive(Debug, Subcommand, Clone, EnumVariantType, Variants)]
 #[variants_trait(CliSubcommand)]
 pub enum ManageCommands {
+    /// Change visibility, accepted mime types or size limit of a bucket, or delete it, and
+    /// generate relevant migrations
+    #[evt(derive(Args, Debug))]
+    Buckets {},
+
     /// Toggle realtime on/off on selected tables and generate relevant migrations
     #[evt(derive(Args, Debug))]
     Realtime {
-        #[arg(long, default_value = "public")]
-        schema: String,
+        /// Schemas to list tables from, can be repeated or comma separated, defaults to
+        /// `db.schema` from the config
+        #[arg(long, value_delimiter = ',')]
+        schema: Vec<String>,
+
+        /// Publication to manage, other than realtime one it is created if it doesn't exist
+        #[arg(long, default_value = "supabase_realtime")]
+        publication: String,
+
+        /// Tables to enable without prompting, as `table` or `schema.table`
+        #[arg(long, num_args = 1..)]
+        enable: Vec<String>,
+
+        /// Tables to disable without prompting, as `table` or `schema.table`
+        #[arg(long, num_args = 1..)]
+        disable: Vec<String>,
+
+        /// Run the migration immediately and set it to applied
+        #[arg(long, conflicts_with = "no_apply")]
+        apply: bool,
+
+        /// Only create the migration file
+        #[arg(long)]
+        no_apply: bool,
     },
 
     /// Manage the register of run migrations
@@ -24,5 +51,6 @@ pub enum ManageCommands {
     },
 }
 
+mod buckets;
 mod migrations;
 mod realtime;
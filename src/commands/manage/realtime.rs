@@ -1,41 +1,188 @@
 use crate::commands::prelude::*;
+use anyhow::Context;
 
 use crate::{
-    abstraction::{RealtimeChange, SupabaseProject},
+    abstraction::{REALTIME_PUBLICATION, RealtimeChange, SupabaseProject, TableRef},
     commands::manage::Realtime,
+    config::CONFIG,
+    sys::is_interactive,
 };
 
+impl Realtime {
+    fn apply(&self) -> Option<bool> {
+        if self.apply {
+            Some(true)
+        } else if self.no_apply {
+            Some(false)
+        } else {
+            CONFIG.migrations.auto_apply
+        }
+    }
+
+    /// Schemas passed with `--schema` along with the ones `--enable`/`--disable` tables are
+    /// qualified with
+    fn schemas(&self) -> Vec<String> {
+        let mut schemas = if self.schema.is_empty() {
+            vec![CONFIG.db.schema.clone()]
+        } else {
+            self.schema.clone()
+        };
+
+        for name in self.enable.iter().chain(&self.disable) {
+            let Some((schema, _)) = name.split_once('.') else {
+                continue;
+            };
+
+            if !schemas.iter().any(|known| known == schema) {
+                schemas.push(schema.to_string());
+            }
+        }
+
+        schemas
+    }
+
+    /// Looks up `table` or `schema.table` names, unqualified ones in the first schema
+    fn resolve(
+        names: &[String],
+        tables: &[TableRef],
+        default_schema: &str,
+    ) -> anyhow::Result<Vec<TableRef>> {
+        names
+            .iter()
+            .map(|name| {
+                let (schema, table) = name.split_once('.').unwrap_or((default_schema, name));
+
+                let Some(found) = tables
+                    .iter()
+                    .find(|candidate| candidate.schema == schema && candidate.name == table)
+                else {
+                    crate::styled_bail!(
+                        "Table `{}` doesn't exist in `{}` schema",
+                        (table, "id"),
+                        (schema, "id")
+                    );
+                };
+
+                Ok(found.clone())
+            })
+            .collect()
+    }
+}
+
 #[async_trait]
 impl CliSubcommand for Realtime {
     async fn run(self: Box<Self>) -> anyhow::Result<()> {
         let project = SupabaseProject::from_cwd().await?;
 
-        let tables = project.tables(&self.schema).await?;
-        let enabled_for = project.realtime_tables(&self.schema).await?;
+        let schemas = self.schemas();
+        let apply = self.apply();
+        let publication = self.publication.clone();
+        let exists = project.publication_exists(&publication).await?;
+
+        if !exists && publication == REALTIME_PUBLICATION {
+            crate::styled_bail!(
+                "The `{}` publication doesn't exist, make sure realtime is enabled in `{}`",
+                (REALTIME_PUBLICATION, "id"),
+                ("supabase/config.toml", "file_path")
+            );
+        }
+
+        let tables = project.tables(&schemas).await?;
+        let published = project.publication_tables(&publication, &schemas).await?;
 
         if tables.is_empty() {
             crate::styled_bail!("You don't seem to have any tables");
         }
 
+        if !self.enable.is_empty() || !self.disable.is_empty() {
+            let enable = Self::resolve(&self.enable, &tables, &schemas[0])?;
+            let disable = Self::resolve(&self.disable, &tables, &schemas[0])?;
+
+            if let Some(table) = enable.iter().find(|table| disable.contains(table)) {
+                crate::styled_bail!(
+                    "Table `{}` can't be both enabled and disabled",
+                    (&table.to_string(), "id")
+                );
+            }
+
+            let rt_change =
+                RealtimeChange::toggle(&publication, !exists, published, &enable, &disable);
+
+            if rt_change.is_empty() {
+                supercli::info!(" No changes to apply, tables are already in the desired state.");
+                return Ok(());
+            }
+
+            let shall_run = match apply {
+                Some(shall_run) => shall_run,
+                None if !is_interactive() => {
+                    crate::styled_bail!(
+                        "Pass `{}` or `{}` to decide whether to run the migration immediately",
+                        ("--apply", "command"),
+                        ("--no-apply", "command")
+                    );
+                }
+                None => use_promptuity!(promptuity => {
+                    promptuity
+                        .with_intro(format!("Realtime ({})", project.id()))
+                        .begin()
+                        .context("Failed to start interactive mode")?;
+
+                    let shall_run = promptuity
+                        .prompt(
+                            Confirm::new(
+                                "Would you like to run this migration immediately and set it to applied?",
+                            )
+                            .with_default(true),
+                        )
+                        .unwrap_or_else(|_| exit(0));
+
+                    let _ = promptuity.finish();
+
+                    shall_run
+                }),
+            };
+
+            project.create_migration(rt_change, shall_run, shall_run).await?;
+            supercli::success!("Migration file created successfully!");
+
+            return Ok(());
+        }
+
+        if !is_interactive() {
+            crate::styled_bail!(
+                "Pass tables with `{}` or `{}` or run the command in a terminal",
+                ("--enable", "command"),
+                ("--disable", "command")
+            );
+        }
+
+        let identities = project.replica_identities(&schemas).await?;
+
         let (rt_change, shall_run) = use_promptuity!(promptuity => {
             let Ok(rt_change) = RealtimeChange::new_interactively(
                 &mut promptuity,
-                &self.schema,
+                &publication,
+                !exists,
                 tables,
-                enabled_for,
+                published,
+                identities,
                 project.id()
             ) else {
                 return Ok(());
             };
 
-            let shall_run = promptuity
-                .prompt(
-                    Confirm::new(
-                        "Would you like to run this migration immediately and set it to applied?",
+            let shall_run = match apply {
+                Some(shall_run) => shall_run,
+                None => promptuity
+                    .prompt(
+                        Confirm::new(
+                            "Would you like to run this migration immediately and set it to applied?",
+                        )
+                        .with_default(true),
                     )
-                    .with_default(true),
-                )
-                .unwrap_or_else(|_| exit(0));
+                    .unwrap_or_else(|_| exit(0)),
+            };
 
             let _ = promptuity.finish();
 
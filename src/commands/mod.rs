@@ -1,9 +1,12 @@
 mod completions;
+pub mod config;
 pub mod create;
 pub mod db;
+mod doctor;
 pub mod manage;
 mod prelude;
+mod render;
 mod running;
 mod stop_any;
 mod upgrade;
-mod watch;
+pub mod watch;
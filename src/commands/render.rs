@@ -0,0 +1,20 @@
+use super::prelude::*;
+use crate::{abstraction::render_template, config::CONFIG};
+
+use anyhow::Context;
+
+#[async_trait]
+impl CliSubcommand for Render {
+    async fn run(self: Box<Self>) -> anyhow::Result<()> {
+        let template = std::fs::read_to_string(&self.file).with_context(|| {
+            styled_error!(
+                "Make sure the file at {} exists and is a valid UTF-8 file",
+                (&self.file.to_string_lossy(), "file_path")
+            )
+        })?;
+
+        print!("{}", render_template(&template, &CONFIG.vars)?.text);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use crate::commands::prelude::*;
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    abstraction::{ExecutionRecord, SupabaseProject},
+    commands::watch::Log,
+};
+
+#[async_trait]
+impl CliSubcommand for Log {
+    async fn run(self: Box<Self>) -> anyhow::Result<()> {
+        let project = SupabaseProject::find()?;
+
+        let records = ExecutionRecord::read_all(&project)?
+            .into_iter()
+            .filter(|record| !self.failed || record.error.is_some())
+            .collect::<Vec<_>>();
+
+        if records.is_empty() {
+            supercli::info!(" No runs of watched files recorded yet.");
+            return Ok(());
+        }
+
+        let skip = records.len().saturating_sub(self.limit);
+
+        for record in records.into_iter().skip(skip) {
+            let time = DateTime::parse_from_rfc3339(&record.time)
+                .map(|time| {
+                    time.with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string()
+                })
+                .unwrap_or(record.time);
+
+            let hash = &record.hash[..record.hash.len().min(8)];
+
+            match record.error {
+                None => supercli::styled!(
+                    "{} {} {} {}",
+                    ("✔", "success_symbol"),
+                    (&time, "dimmed"),
+                    (&record.file, "file_path"),
+                    (hash, "muted")
+                ),
+                Some(error) => {
+                    supercli::styled!(
+                        "{} {} {} {}",
+                        ("✘", "error"),
+                        (&time, "dimmed"),
+                        (&record.file, "file_path"),
+                        (hash, "muted")
+                    );
+
+                    supercli::styled!(
+                        "  {}",
+                        (error.lines().next().unwrap_or_default(), "error")
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
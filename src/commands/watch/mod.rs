@@ -0,0 +1,489 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use super::prelude::*;
+use crate::{
+    abstraction::{
+        CodeWatch, CreatedObject, ExecutionRecord, PathFilter, ScriptError, SqlFile,
+        SupabaseProject, WatchState, checksum, render_template, split_statements,
+    },
+    config::CONFIG,
+    errors::NoWay,
+};
+
+use anyhow::Context;
+use clap::{Args, Subcommand};
+use derive_setters::Setters;
+use enum_variant_type::EnumVariantType;
+use evt_trait_object::Variants;
+use futures_channel::mpsc::Sender;
+use futures_util::{StreamExt, sink::SinkExt};
+use tokio::{fs::File, io::AsyncReadExt};
+
+#[derive(Setters, Default, Clone)]
+pub struct SqlFileExecutor {
+    /// Whether functions, procedures, views and triggers created by a file are dropped before
+    /// it's run
+    autodrop: bool,
+
+    /// Whether each file is run within a transaction, files with statements that can't run
+    /// in one (e.g. `create index concurrently`) are run without it anyway
+    transactional: bool,
+
+    /// Whether files unchanged since their last successful run are run anyway
+    force: bool,
+
+    /// Paths listed in manifests of watched directories, used to order batches of changes
+    manifest: Vec<PathBuf>,
+
+    format: OutputFormat,
+}
+
+enum FileRun {
+    Executed,
+    Unchanged,
+}
+
+impl SqlFileExecutor {
+    async fn execute(&self, project: &SupabaseProject, sql: &str) -> anyhow::Result<()> {
+        let statements = split_statements(sql);
+
+        let prelude = if self.autodrop {
+            CreatedObject::drop_statements(project, &statements).await?
+        } else {
+            Vec::new()
+        };
+
+        let transactional = self.transactional
+            && !statements
+                .iter()
+                .any(|statement| statement.requires_no_transaction());
+
+        // Dropped objects only come back when the file fails if the drops are rolled back
+        // along with it
+        if !prelude.is_empty() && !transactional {
+            crate::styled_bail!(
+                "The file can't run in a transaction (e.g. due to `{}`), which `{}` requires, run it without the flag",
+                ("create index concurrently", "command"),
+                ("--autodrop", "command")
+            );
+        }
+
+        project.runtime().script(&prelude, sql, transactional).await
+    }
+
+    async fn run_file(&self, project: &SupabaseProject, path: &Path) -> anyhow::Result<FileRun> {
+        let mut file = File::open(path).await.with_context(|| {
+            styled_error!(
+                "Failed to open the file at {}",
+                (&path.to_string_lossy(), "file_path")
+            )
+        })?;
+
+        let mut sql = String::new();
+
+        file.read_to_string(&mut sql).await.with_context(|| {
+            styled_error!(
+                "Make sure the file at {} is a valid UTF-8 file",
+                (&path.to_string_lossy(), "file_path")
+            )
+        })?;
+
+        let rendered = render_template(&sql, &CONFIG.vars)?;
+
+        let key = WatchState::key(project, path);
+        let hash = checksum(&rendered.text);
+
+        if !self.force && WatchState::load(project)?.executed.get(&key) == Some(&hash) {
+            return Ok(FileRun::Unchanged);
+        }
+
+        // Failures point at the file rather than the rendered SQL
+        let result = self
+            .execute(project, &rendered.text)
+            .await
+            .map_err(|mut error| {
+                if let Some(script_error) = error.downcast_mut::<ScriptError>() {
+                    script_error.relocate(&sql, rendered.template_offset(script_error.offset));
+                }
+
+                error
+            });
+
+        ExecutionRecord::new(
+            key.clone(),
+            hash.clone(),
+            result.as_ref().err().map(|error| error.to_string()),
+        )
+        .append(project)?;
+
+        result?;
+
+        WatchState::update(project, |state| {
+            state.executed.insert(key, hash);
+        })?;
+
+        Ok(FileRun::Executed)
+    }
+
+    /// Orders a batch of changed files by their dependencies, unreadable ones go last to be
+    /// reported when run
+    fn order(&self, paths: Vec<Arc<PathBuf>>) -> Vec<Arc<PathBuf>> {
+        if paths.len() < 2 {
+            return paths;
+        }
+
+        let mut files = Vec::new();
+        let mut unreadable = Vec::new();
+
+        for path in paths {
+            match SqlFile::read(path.to_path_buf()) {
+                Ok(file) => files.push(file),
+                Err(_) => unreadable.push(path),
+            }
+        }
+
+        SqlFile::order(files, &self.manifest)
+            .into_iter()
+            .map(|file| Arc::new(file.path))
+            .chain(unreadable)
+            .collect()
+    }
+
+    pub fn start(self, project: SupabaseProject) -> Sender<ExecuteEvent> {
+        let (execute_queuer, mut execute_queue) =
+            futures_channel::mpsc::channel::<ExecuteEvent>(1024);
+
+        let project = Arc::new(project);
+
+        tokio::spawn(async move {
+            let project = Arc::clone(&project);
+
+            while let Some(ExecuteEvent {
+                paths,
+                immediate_run,
+            }) = execute_queue.next().await
+            {
+                let batch = paths.len() > 1;
+                let format = self.format;
+
+                let mut pending = if immediate_run {
+                    paths
+                } else {
+                    self.order(paths)
+                };
+
+                let total = pending.len();
+                let mut executed = 0;
+                let mut unchanged = 0;
+                let mut retry = false;
+
+                if batch && !immediate_run {
+                    format.report(WatchEvent::Batch { files: total });
+                }
+
+                // Files of a batch failing e.g. due to a missing dependency are retried as
+                // long as the previous pass has brought any progress
+                let failed = loop {
+                    let mut failed = Vec::new();
+
+                    for path in &pending {
+                        let file = format.file(path);
+
+                        let trigger = if retry {
+                            Trigger::Retry
+                        } else if immediate_run {
+                            Trigger::Immediate
+                        } else {
+                            Trigger::Change
+                        };
+
+                        format.report(WatchEvent::Started {
+                            file: file.clone(),
+                            trigger,
+                        });
+
+                        match self.run_file(&project, path).await {
+                            Err(err) => {
+                                // Errors of a batch are reported once it's known they won't
+                                // be retried anymore
+                                if batch {
+                                    format.report(WatchEvent::Deferred { file });
+                                } else {
+                                    format.report(WatchEvent::failed(file, &err, false));
+                                }
+
+                                failed.push((Arc::clone(path), err));
+                            }
+                            Ok(FileRun::Unchanged) => {
+                                unchanged += 1;
+                                format.report(WatchEvent::Skipped { file });
+                            }
+                            Ok(FileRun::Executed) => {
+                                executed += 1;
+                                format.report(WatchEvent::Executed { file });
+                            }
+                        }
+                    }
+
+                    if failed.is_empty() || !batch {
+                        break failed;
+                    }
+
+                    if failed.len() == pending.len() {
+                        for (path, err) in &failed {
+                            format.report(WatchEvent::failed(format.file(path), err, true));
+                        }
+
+                        break failed;
+                    }
+
+                    pending = failed.into_iter().map(|(path, _)| path).collect();
+                    retry = true;
+                };
+
+                if batch {
+                    format.report(WatchEvent::Summary {
+                        files: total,
+                        executed,
+                        unchanged,
+                        failed: failed.len(),
+                    });
+                }
+            }
+        });
+
+        execute_queuer
+    }
+}
+
+/// Files to run one after another, in order
+#[derive(Default, PartialEq, Eq, Hash)]
+pub struct ExecuteEvent {
+    paths: Vec<Arc<PathBuf>>,
+    immediate_run: bool,
+}
+
+impl ExecuteEvent {
+    pub fn immediate(paths: Vec<Arc<PathBuf>>) -> Self {
+        Self {
+            paths,
+            immediate_run: true,
+        }
+    }
+
+    pub fn watched(paths: Vec<Arc<PathBuf>>) -> Self {
+        Self {
+            paths,
+            immediate_run: false,
+        }
+    }
+}
+
+/// Files to watch or promote, falling back to the `watch` section of the config
+#[derive(Debug, Clone, Args)]
+pub struct FileSelection {
+    /// Directories or files to watch, default to `watch.paths` (or `watch.directory`) from the
+    /// config
+    #[arg()]
+    paths: Vec<String>,
+
+    /// Glob pattern of files to include, relative to the watched path, can be repeated,
+    /// defaults to `watch.include` from the config or `*.sql`
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Glob pattern of files to skip, e.g. `*.draft.sql`, can be repeated, defaults to
+    /// `watch.exclude` from the config
+    #[arg(long)]
+    exclude: Vec<String>,
+}
+
+impl FileSelection {
+    fn filter(self) -> anyhow::Result<PathFilter> {
+        let or_config = |passed: Vec<String>, configured: &Vec<String>| {
+            if passed.is_empty() {
+                configured.clone()
+            } else {
+                passed
+            }
+        };
+
+        let mut paths = or_config(self.paths, &CONFIG.watch.paths);
+
+        if paths.is_empty() {
+            paths.extend(CONFIG.watch.directory.clone());
+        }
+
+        if paths.is_empty() {
+            crate::styled_bail!(
+                "Nothing to watch, pass paths as arguments or set `{}` in `{}`",
+                ("watch.paths", "property"),
+                ("sbp.toml", "file_path")
+            );
+        }
+
+        PathFilter::new(
+            &paths,
+            &or_config(self.include, &CONFIG.watch.include),
+            &or_config(self.exclude, &CONFIG.watch.exclude),
+        )
+    }
+}
+
+/// Paths listed in manifests of every watched directory
+fn manifest(filter: &PathFilter) -> anyhow::Result<Vec<PathBuf>> {
+    let mut manifest = Vec::new();
+
+    for root in filter.roots() {
+        let root = Path::new(&root);
+
+        if root.is_dir() {
+            manifest.extend(SqlFile::manifest(root)?);
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Selected files ordered by their dependencies, along with the ones that couldn't be read,
+/// so a single unreadable file doesn't stop the rest
+fn ordered_files(
+    filter: &PathFilter,
+) -> anyhow::Result<(Vec<SqlFile>, Vec<(PathBuf, anyhow::Error)>)> {
+    let mut files = Vec::new();
+    let mut unreadable = Vec::new();
+
+    for path in filter.files()? {
+        match SqlFile::read(path.clone()) {
+            Ok(file) => files.push(file),
+            Err(error) => unreadable.push((path, error)),
+        }
+    }
+
+    Ok((SqlFile::order(files, &manifest(filter)?), unreadable))
+}
+
+fn parse_var(value: &str) -> Result<(String, String), String> {
+    let Some((name, value)) = value.split_once('=') else {
+        return Err(format!("`{value}` should be in the form of NAME=VALUE"));
+    };
+
+    Ok((name.trim().to_string(), value.to_string()))
+}
+
+#[async_trait]
+impl CliSubcommand for Watch {
+    async fn run(self: Box<Self>) -> anyhow::Result<()> {
+        if let Some(command) = self.command {
+            return command.to_object().run().await;
+        }
+
+        let autodrop = self.autodrop || CONFIG.watch.autodrop;
+        let transactional = !self.no_transaction && CONFIG.watch.transaction;
+
+        if autodrop && !transactional {
+            crate::styled_bail!(
+                "`{}` runs the drops in the same transaction as the file, it can't be combined with `{}` (or `{}`)",
+                ("--autodrop", "command"),
+                ("--no-transaction", "command"),
+                ("watch.transaction = false", "property")
+            );
+        }
+
+        let filter = self.selection.filter()?;
+        let project = SupabaseProject::from_cwd().await?;
+
+        let mut queuer = SqlFileExecutor::default()
+            .autodrop(autodrop)
+            .transactional(transactional)
+            .force(self.force)
+            .manifest(manifest(&filter)?)
+            .format(self.format)
+            .start(project.clone());
+
+        let codewatch = CodeWatch::default()
+            .filter(filter.clone())
+            .debounce(Duration::from_millis(
+                self.debounce.unwrap_or(CONFIG.watch.debounce),
+            ))
+            .queuer(queuer.clone())
+            .build(&filter.roots(), ExecuteEvent::watched)?;
+
+        self.format.report(WatchEvent::Watching {
+            project: project.id().to_string(),
+            paths: filter.roots(),
+        });
+
+        if self.immediate {
+            let (files, unreadable) = ordered_files(&filter)?;
+
+            // Unreadable files go last to be reported when run, like in batches of changes
+            let paths = files
+                .into_iter()
+                .map(|file| file.path)
+                .chain(unreadable.into_iter().map(|(path, _)| path))
+                .map(Arc::new)
+                .collect::<Vec<_>>();
+
+            if !paths.is_empty() {
+                queuer
+                    .send(ExecuteEvent::immediate(paths))
+                    .await
+                    .no_way_because("the receiver should still be alive by design");
+            }
+        }
+
+        codewatch
+            .run()
+            .await
+            .no_way_because("all the errors inside of tasks should be handled")
+            .context("the file watcher has failed")?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Subcommand, Clone, EnumVariantType, Variants)]
+#[variants_trait(CliSubcommand)]
+pub enum WatchCommands {
+    /// Create a migration out of watched files changed since their last promotion and mark it
+    /// as applied locally
+    #[evt(derive(Args, Debug))]
+    Promote {
+        #[command(flatten)]
+        selection: FileSelection,
+
+        /// Name of the migration
+        #[arg(long, default_value = "promote_watched_files")]
+        name: String,
+
+        /// Value of a `{{ name }}` placeholder for the environment the migration targets, e.g.
+        /// `--var schema=app`, can be repeated, local `[vars]` and environment variables aren't
+        /// used when promoting
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+    },
+
+    /// Show the most recent runs of watched files
+    #[evt(derive(Args, Debug))]
+    Log {
+        /// Number of runs to show
+        #[arg(long, short = 'n', default_value_t = 20)]
+        limit: usize,
+
+        /// Only show failed runs
+        #[arg(long)]
+        failed: bool,
+    },
+}
+
+mod log;
+mod promote;
+mod report;
+
+pub use report::OutputFormat;
+use report::{Trigger, WatchEvent};
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use crate::commands::prelude::*;
+use heck::ToSnakeCase;
+use serde_json::Value;
+
+use crate::{
+    abstraction::{SupabaseProject, WatchState, checksum, render_template_with},
+    commands::watch::{Promote, ordered_files},
+};
+
+#[async_trait]
+impl CliSubcommand for Promote {
+    async fn run(self: Box<Self>) -> anyhow::Result<()> {
+        let filter = self.selection.filter()?;
+        let project = SupabaseProject::from_cwd().await?;
+
+        let state = WatchState::load(&project)?;
+
+        // Local values are meant for the local database only, the migration gets ones passed
+        // for its target environment
+        let vars = self
+            .vars
+            .into_iter()
+            .map(|(name, value)| (name, Value::String(value)))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut changed = Vec::new();
+        let (files, unreadable) = ordered_files(&filter)?;
+
+        // Left out of the migration, they're promoted once they can be read
+        for (_, error) in &unreadable {
+            supercli::error!(&format!("Error: {}", error));
+        }
+
+        for file in files {
+            let key = WatchState::key(&project, &file.path);
+            let sql = match render_template_with(&file.sql, &vars, false) {
+                Ok(rendered) => rendered.text,
+                Err(missing) => crate::styled_bail!(
+                    "No value for `{}` at line {} of {}, pass it with `{}`",
+                    (&missing.name, "id"),
+                    (&missing.line.to_string(), "number"),
+                    (&key, "file_path"),
+                    (&format!("--var {}=<value>", missing.name), "command")
+                ),
+            };
+
+            let checksum = checksum(&sql);
+
+            if state.promoted.get(&key) != Some(&checksum) {
+                changed.push((sql, key, checksum));
+            }
+        }
+
+        if changed.is_empty() {
+            supercli::info!(" No watched files changed since the last promotion. Nothing to promote.");
+            return Ok(());
+        }
+
+        let sql = changed
+            .iter()
+            .map(|(sql, key, _)| {
+                let sql = sql.trim_end();
+
+                // Makes sure the next file doesn't continue an unterminated statement
+                let terminator = if sql.ends_with(';') { "" } else { "\n;" };
+
+                format!("-- {key}\n{sql}{terminator}\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        for (_, key, _) in &changed {
+            supercli::styled!("📦 Promoting {}", (key, "file_path"));
+        }
+
+        project
+            .create_migration((sql, self.name.to_snake_case()), false, true)
+            .await?;
+
+        WatchState::update(&project, |state| {
+            for (_, key, checksum) in changed {
+                state.promoted.insert(key, checksum);
+            }
+        })?;
+
+        supercli::success!(" Watched files have been promoted to the migration directory!");
+
+        Ok(())
+    }
+}
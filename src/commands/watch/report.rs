@@ -0,0 +1,164 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::abstraction::ScriptError;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+
+    /// Newline-delimited JSON events, for editor integrations
+    Json,
+}
+
+/// Why a file is being run
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    Immediate,
+    Change,
+    Retry,
+}
+
+/// Progress of `sbp watch`, printed either as text or as a JSON line
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WatchEvent {
+    Watching {
+        project: String,
+        paths: Vec<String>,
+    },
+    Batch {
+        files: usize,
+    },
+    Started {
+        file: String,
+        trigger: Trigger,
+    },
+    Executed {
+        file: String,
+    },
+    Skipped {
+        file: String,
+    },
+
+    /// Failed within a batch, run again after the rest of it as long as that brings any
+    /// progress, its error is reported by a single `failed` event once it's not retried anymore
+    Deferred {
+        file: String,
+    },
+    Failed {
+        file: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        sqlstate: Option<String>,
+        message: String,
+        detail: Option<String>,
+        hint: Option<String>,
+
+        #[serde(skip)]
+        report: String,
+
+        #[serde(skip)]
+        in_batch: bool,
+    },
+    Summary {
+        files: usize,
+        executed: usize,
+        unchanged: usize,
+        failed: usize,
+    },
+}
+
+impl WatchEvent {
+    pub fn failed(file: String, error: &anyhow::Error, in_batch: bool) -> Self {
+        let script_error = error.downcast_ref::<ScriptError>();
+
+        Self::Failed {
+            file,
+            line: script_error.map(|error| error.line),
+            column: script_error.map(|error| error.column),
+            sqlstate: script_error.map(|error| error.code.clone()),
+            message: script_error
+                .map(|error| error.message.clone())
+                .unwrap_or_else(|| error.to_string()),
+            detail: script_error.and_then(|error| error.detail.clone()),
+            hint: script_error.and_then(|error| error.hint.clone()),
+            report: error.to_string(),
+            in_batch,
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Path of the file as passed for text, absolute for JSON so editors can match it with
+    /// an open file
+    pub fn file(&self, path: &Path) -> String {
+        let path = match self {
+            Self::Text => path.to_path_buf(),
+            Self::Json => std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+        };
+
+        path.to_string_lossy().into_owned()
+    }
+
+    pub fn report(&self, event: WatchEvent) {
+        if *self == Self::Json {
+            println!("{}", serde_json::to_string(&event).unwrap_or_default());
+            return;
+        }
+
+        match event {
+            WatchEvent::Watching { project, .. } => supercli::styled!(
+                "👁️  Starting sql watch to reflect in project `{}`…\n",
+                (&project, "id")
+            ),
+            WatchEvent::Batch { files } => supercli::styled!(
+                "🔍 Changes observed in {} files, running them in order of dependencies",
+                (&files.to_string(), "number")
+            ),
+            WatchEvent::Started { file, trigger } => match trigger {
+                Trigger::Immediate => {
+                    supercli::styled!("🛫 Executing file immediately ({})", (&file, "file_path"))
+                }
+                Trigger::Change => {
+                    supercli::styled!("🔍 Change observed ({})", (&file, "file_path"))
+                }
+                Trigger::Retry => supercli::styled!("🔁 Retrying file ({})", (&file, "file_path")),
+            },
+            WatchEvent::Executed { .. } => supercli::success!("Query run successfully\n"),
+            WatchEvent::Skipped { .. } => supercli::styled!(
+                "{}\n",
+                ("Unchanged since the last successful run, skipped", "muted")
+            ),
+            WatchEvent::Deferred { .. } => supercli::styled!(
+                "{}\n",
+                ("Failed, held back until the rest of the batch has run", "muted")
+            ),
+            WatchEvent::Failed {
+                file,
+                report,
+                in_batch: true,
+                ..
+            } => supercli::error!(&format!("Error ({}): {}\n", file, report)),
+            WatchEvent::Failed { report, .. } => {
+                supercli::error!(&format!("Error: {}\n", report))
+            }
+            WatchEvent::Summary {
+                files,
+                executed,
+                unchanged,
+                failed,
+            } => supercli::styled!(
+                "📋 {} files: {} run, {} unchanged, {} failed\n",
+                (&files.to_string(), "number"),
+                (&executed.to_string(), "number"),
+                (&unchanged.to_string(), "number"),
+                (&failed.to_string(), "number")
+            ),
+        }
+    }
+}
@@ -1,8 +1,237 @@
-use serde::Deserialize;
+use std::{collections::BTreeMap, path::PathBuf};
+
+use figment::{
+    Figment, Metadata, Profile, Provider,
+    providers::{Format, Toml},
+    value::{Dict, Map, Tag, Value},
+};
+use serde::{Deserialize, Serialize};
 
-#[allow(dead_code)]
 pub static CONFIG_FILENAME: &str = "sbp.toml";
+pub static ENV_PREFIX: &str = "SBP_";
+pub static ENV_PROVIDER_NAME: &str = "environment variable";
+
+lazy_static::lazy_static! {
+    // Lossy so `SBP_*` values, which are always strings, fill numeric and boolean fields too
+    pub(crate) static ref CONFIG: Config = Config::figment()
+        .extract_lossy()
+        .unwrap_or_else(|error| {
+            supercli::error!(&format!("Error: Failed to load configuration\n> {}", error));
+            std::process::exit(1);
+        });
+}
+
+/// Effective `sbp` configuration, merged from (in order of precedence, lowest first) the
+/// global `~/.config/sbp/config.toml`, the closest `sbp.toml` up from cwd and `SBP_*`
+/// environment variables (nesting separated by `__`, e.g. `SBP_DB__SCHEMA`)
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    pub db: DbConfig,
+    pub watch: WatchConfig,
+    pub supabase: SupabaseCliConfig,
+    pub migrations: MigrationsConfig,
+    pub diff: DiffConfig,
+
+    /// Values of `{{ name }}` placeholders in watched files, see [`render_template`]
+    ///
+    /// [`render_template`]: crate::abstraction::render_template
+    pub vars: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct DbConfig {
+    /// Schema used by `db commit` and `manage realtime` when `--schema` isn't passed
+    pub schema: String,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        Self {
+            schema: "public".into(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct WatchConfig {
+    /// Directory watched by `sbp watch` when no path is passed, a shorthand for `paths`
+    pub directory: Option<String>,
+
+    /// Directories or files watched by `sbp watch` when none is passed
+    pub paths: Vec<String>,
+
+    /// Glob patterns of watched files, relative to the watched path, `*` matches across
+    /// directories
+    pub include: Vec<String>,
+
+    /// Glob patterns of files excluded from watching, e.g. `*.draft.sql`
+    pub exclude: Vec<String>,
+
+    /// Whether `sbp watch` always runs as with `--autodrop`
+    pub autodrop: bool,
+
+    /// Whether `sbp watch` runs each file within a transaction, `--no-transaction` overrides it
+    pub transaction: bool,
+
+    /// Milliseconds `sbp watch` waits for further changes before running changed files together
+    pub debounce: u64,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            paths: Vec::new(),
+            include: vec!["*.sql".into()],
+            exclude: Vec::new(),
+            autodrop: false,
+            transaction: true,
+            debounce: 100,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct SupabaseCliConfig {
+    /// Where the `supabase` CLI comes from, see [`CliSource`]
+    pub source: CliSource,
+
+    /// Path to the `supabase` binary, used by the `path` source (and preferred by `auto`)
+    pub path: Option<PathBuf>,
+
+    /// CLI version run through package runners, anything other than `latest` makes `auto`
+    /// skip a globally installed binary as its version can't be guaranteed
+    pub version: String,
+
+    /// Raw command prepended to every CLI invocation, overrides the source detection
+    pub command: Option<String>,
+}
+
+impl Default for SupabaseCliConfig {
+    fn default() -> Self {
+        Self {
+            source: CliSource::Auto,
+            path: None,
+            version: "latest".into(),
+            command: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub(crate) enum CliSource {
+    /// First available of: `path`, `global`, `bunx`, `pnpm`, `npx`
+    Auto,
+    Path,
+    Global,
+    Bunx,
+    Pnpm,
+    Npx,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default)]
+pub(crate) struct MigrationsConfig {
+    /// Whether generated migrations are run and marked as applied right away, when unset
+    /// the user is asked every time
+    pub auto_apply: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct DiffConfig {
+    /// Whether `db commit` diffs catalogs on its own instead of running `supabase db diff`, it
+    /// falls back to the CLI when objects it doesn't support have changed
+    pub native: bool,
+
+    /// Port of the shadow database the native diff keeps running between runs
+    pub shadow_port: u16,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            native: false,
+            shadow_port: 54319,
+        }
+    }
+}
+
+impl Config {
+    pub fn figment() -> Figment {
+        let mut figment = Figment::new();
+
+        if let Some(global) = Self::global_path() {
+            figment = figment.merge(Toml::file(global));
+        }
+
+        // Relative paths are looked up in cwd and then in every parent directory
+        figment.merge(Toml::file(CONFIG_FILENAME)).merge(Environment)
+    }
+
+    pub fn global_path() -> Option<PathBuf> {
+        let mut path = homedir::my_home().ok().flatten()?;
+        path.push(".config/sbp/config.toml");
+
+        Some(path)
+    }
+}
+
+/// `SBP_*` environment variables provider, a lightweight stand-in for figment's `Env` which
+/// would require enabling an extra feature along with its dependencies
+struct Environment;
+
+impl Environment {
+    fn insert(dict: &mut Dict, path: &[&str], value: Value) {
+        let [key, rest @ ..] = path else {
+            return;
+        };
+
+        if rest.is_empty() {
+            dict.insert(key.to_string(), value);
+            return;
+        }
+
+        let entry = dict
+            .entry(key.to_string())
+            .or_insert_with(|| Value::Dict(Tag::Default, Dict::new()));
+
+        if let Value::Dict(_, nested) = entry {
+            Self::insert(nested, rest, value);
+        }
+    }
+}
+
+impl Provider for Environment {
+    fn metadata(&self) -> Metadata {
+        Metadata::named(ENV_PROVIDER_NAME)
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        let mut dict = Dict::new();
+
+        // Values are kept as strings, they're coerced into the fields' types on extraction
+        for (key, value) in std::env::vars_os() {
+            let (Some(key), Some(value)) = (key.to_str(), value.to_str()) else {
+                continue;
+            };
+
+            let Some(key) = key.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+
+            let key = key.to_lowercase();
+            let path = key.split("__").collect::<Vec<_>>();
+
+            Self::insert(&mut dict, &path, value.into());
+        }
 
-#[allow(dead_code)]
-#[derive(Deserialize, Debug)]
-pub(crate) struct Config {}
+        Ok(Profile::Default.collect(dict))
+    }
+}
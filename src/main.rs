@@ -15,18 +15,7 @@ mod patched;
 mod sys;
 
 use crate::cli::Cli;
-use crate::config::{CONFIG_FILENAME, Config};
 use clap::Parser;
-use figment::Figment;
-use figment::providers::{Format, Toml};
-use lazy_static::lazy_static;
-
-lazy_static! {
-    static ref CONFIG: Option<Config> = Figment::new()
-        .merge(Toml::file(CONFIG_FILENAME))
-        .extract()
-        .ok();
-}
 
 #[tokio::main]
 async fn main() {
@@ -1,6 +1,12 @@
-use crate::config::CONFIG_FILENAME;
+use std::io::IsTerminal;
+use std::path::PathBuf;
 
-pub fn run_before_hook() {}
+use crate::config::{CONFIG, CONFIG_FILENAME};
+
+pub fn run_before_hook() {
+    // Surfaces configuration errors before any command starts doing its job
+    lazy_static::initialize(&CONFIG);
+}
 
 #[allow(dead_code)]
 pub fn does_config_exist() -> bool {
@@ -12,3 +18,20 @@ pub fn does_config_exist() -> bool {
 
     config_path.is_file()
 }
+
+/// Looks the executable up in `PATH` directories, like `which` does
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&paths).find_map(|directory| {
+        [name.to_string(), format!("{name}.exe"), format!("{name}.cmd")]
+            .into_iter()
+            .map(|candidate| directory.join(candidate))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Whether prompts can be shown, they're rendered to stderr and read from stdin
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
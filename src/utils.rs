@@ -27,3 +27,28 @@ pub fn escape_for_sh_double_quotes(value: &str) -> String {
 
     out
 }
+
+/// Parses a size given in bytes or with a unit, decimal (`KB`, `MB`, `GB`) or binary (`KiB`,
+/// `MiB`, `GiB`), e.g. `5MB` or `512 KiB`
+pub fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let split_at = value
+        .find(|char: char| !char.is_ascii_digit())
+        .unwrap_or(value.len());
+
+    let (number, unit) = value.split_at(split_at);
+    let number = number.parse::<u64>().ok()?;
+
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1_000,
+        "m" | "mb" => 1_000_000,
+        "g" | "gb" => 1_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        _ => return None,
+    };
+
+    number.checked_mul(multiplier)
+}